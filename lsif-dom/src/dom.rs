@@ -1,13 +1,16 @@
-use crate::{
+use lsif_parser_lib::{
     syntax::{SyntaxElement, SyntaxKind, SyntaxKind::*, SyntaxNode, SyntaxToken},
-    util::{unescape, StringExt},
+    util::unquote,
 };
-use indexmap::IndexMap;
-use rowan::{TextRange, TextSize};
-use std::{hash::Hash, iter::FromIterator, mem, rc::Rc};
+use rowan::{NodeOrToken, TextRange};
+use std::{borrow::Cow, collections::HashMap};
 
 #[macro_use]
 mod macros;
+mod check;
+mod endpoints;
+mod events;
+mod schema;
 
 /// Casting allows constructing DOM nodes from syntax nodes.
 pub trait Cast: Sized + private::Sealed {
@@ -31,15 +34,17 @@ mod private {
     dom_sealed!(
         Node,
         RootNode,
+        ElementNode,
         VertexNode,
-        // EdgeNode,
-        // EntryNode,
-        // KeyNode,
-        // ValueNode,
-        // ArrayNode,
-        // IntegerNode,
-        // StringNode,
-        // BoolNode,
+        EdgeNode,
+        EntryNode,
+        KeyNode,
+        ValueNode,
+        ArrayNode,
+        TableNode,
+        IntegerNode,
+        StringNode,
+        BoolNode
     );
 }
 
@@ -47,13 +52,19 @@ mod private {
 pub enum Node {
     Root(RootNode),
     Vertex(VertexNode),
+    Edge(EdgeNode),
     Entry(EntryNode),
+    Key(KeyNode),
+    Value(ValueNode),
 }
 
 dom_node_from!(
     RootNode => Root,
     VertexNode => Vertex,
+    EdgeNode => Edge,
     EntryNode => Entry,
+    KeyNode => Key,
+    ValueNode => Value
 );
 
 impl core::fmt::Display for Node {
@@ -61,7 +72,10 @@ impl core::fmt::Display for Node {
         match self {
             Node::Root(v) => v.fmt(f),
             Node::Vertex(v) => v.fmt(f),
+            Node::Edge(v) => v.fmt(f),
             Node::Entry(v) => v.fmt(f),
+            Node::Key(v) => v.fmt(f),
+            Node::Value(v) => v.fmt(f),
         }
     }
 }
@@ -71,7 +85,10 @@ impl Common for Node {
         match self {
             Node::Root(v) => v.syntax(),
             Node::Vertex(v) => v.syntax(),
+            Node::Edge(v) => v.syntax(),
             Node::Entry(v) => v.syntax(),
+            Node::Key(v) => v.syntax(),
+            Node::Value(v) => v.syntax(),
         }
     }
 
@@ -79,7 +96,10 @@ impl Common for Node {
         match self {
             Node::Root(v) => v.text_range(),
             Node::Vertex(v) => v.text_range(),
-            Node::Entry(v) => v.text_ragne()
+            Node::Edge(v) => v.text_range(),
+            Node::Entry(v) => v.text_range(),
+            Node::Key(v) => v.text_range(),
+            Node::Value(v) => v.text_range(),
         }
     }
 
@@ -87,7 +107,10 @@ impl Common for Node {
         match self {
             Node::Root(v) => v.is_valid(),
             Node::Vertex(v) => v.is_valid(),
+            Node::Edge(v) => v.is_valid(),
             Node::Entry(v) => v.is_valid(),
+            Node::Key(v) => v.is_valid(),
+            Node::Value(v) => v.is_valid(),
         }
     }
 }
@@ -95,63 +118,71 @@ impl Common for Node {
 impl Cast for Node {
     fn cast(element: SyntaxElement) -> Option<Self> {
         match element.kind() {
-            // STRING
-            // | INTEGER
-            // | FLOAT
-//             | BOOL
-//             => ValueNode::dom_inner(element).map(Node::Value),
-//             KEY => KeyNode::cast(element).map(Node::Key),
-//             VALUE => ValueNode::cast(element).map(Node::Value),
-//             TABLE_HEADER | TABLE_ARRAY_HEADER => TableNode::cast(element).map(Node::Table),
+            STRING | INTEGER | BOOL | VALUE | LIST | TABLE => {
+                ValueNode::cast(element).map(Node::Value)
+            }
+            KEY => KeyNode::cast(element).map(Node::Key),
             ENTRY => EntryNode::cast(element).map(Node::Entry),
-            VERTEX => EntryNode::cast(element).map(Node::Vertex),
-//             ARRAY => ArrayNode::cast(element).map(Node::Array),
-//             ROOT => RootNode::cast(element).map(Node::Root),
+            SENTENCE => ElementNode::cast(element).map(|e| match e {
+                ElementNode::Vertex(v) => Node::Vertex(v),
+                ElementNode::Edge(v) => Node::Edge(v),
+            }),
+            ROOT => RootNode::cast(element).map(Node::Root),
             _ => None,
         }
     }
 }
 
 impl Node {
-    pub fn text_range(&self) -> TextRange {
-        match self {
-            Node::Root(v) => v.text_range(),
-            Node::Vertex(v) => v.text_range(),
-            // Node::Table(v) => v.text_range(),
-            Node::Entry(v) => v.text_range(),
-            // Node::Key(v) => v.text_range(),
-            // Node::Value(v) => v.text_range(),
-            // Node::Array(v) => v.text_range(),
-        }
-    }
-
     pub fn kind(&self) -> SyntaxKind {
-        match self {
-            Node::Root(v) => v.syntax().kind(),
-            Node::Vertex(v) => v.syntax().kind(),
-            // Node::Table(v) => v.syntax().kind(),
-            Node::Entry(v) => v.syntax().kind(),
-            // Node::Key(v) => v.syntax().kind(),
-            // Node::Value(v) => v.syntax().kind(),
-            // Node::Array(v) => v.syntax().kind(),
-        }
+        self.syntax().kind()
     }
 }
 
 dom_display!(
     RootNode,
+    ElementNode,
     VertexNode,
-    // TableNode,
+    EdgeNode,
     EntryNode,
-    // ArrayNode,
-    // IntegerNode,
-    // StringNode
+    KeyNode,
+    ValueNode,
+    ArrayNode,
+    TableNode
 );
 
+/// The options of the semantic checks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Options {
+    /// Whether `1` and `"1"` are different ids, as in the specification.
+    ///
+    /// Some indexers mix both forms for the same element,
+    /// those dumps can be checked with this disabled.
+    pub distinct_id_types: bool,
+    /// Whether the dump is checked as a stream, where vertices
+    /// must be emitted before the edges that reference them.
+    ///
+    /// Otherwise edges can reference vertices anywhere in the dump.
+    pub streaming: bool,
+    /// Whether properties that are not in the specification are allowed,
+    /// e.g. the extensions of some indexers.
+    pub allow_unknown_properties: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            distinct_id_types: true,
+            streaming: false,
+            allow_unknown_properties: false,
+        }
+    }
+}
+
 /// The root of the DOM.
 ///
 /// If any errors occur, the tree might be
-/// missing entries, or will be completely empty.
+/// missing elements, or will be completely empty.
 ///
 /// Syntax errors are **not** reported, those have to
 /// be checked before constructing the DOM.
@@ -159,16 +190,31 @@ dom_display!(
 pub struct RootNode {
     syntax: SyntaxNode,
     errors: Vec<Error>,
-    entries: Entries,
+    elements: Vec<ElementNode>,
 }
 
 impl RootNode {
-    pub fn entries(&self) -> &Entries {
-        &self.entries
+    /// The vertices and edges, in the order of the dump.
+    pub fn elements(&self) -> &[ElementNode] {
+        &self.elements
     }
 
-    pub fn into_entries(self) -> Entries {
-        self.entries
+    pub fn into_elements(self) -> Vec<ElementNode> {
+        self.elements
+    }
+
+    pub fn vertices(&self) -> impl Iterator<Item = &VertexNode> {
+        self.elements.iter().filter_map(|e| match e {
+            ElementNode::Vertex(v) => Some(v),
+            ElementNode::Edge(_) => None,
+        })
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = &EdgeNode> {
+        self.elements.iter().filter_map(|e| match e {
+            ElementNode::Edge(v) => Some(v),
+            ElementNode::Vertex(_) => None,
+        })
     }
 
     pub fn errors(&self) -> &[Error] {
@@ -186,50 +232,749 @@ impl Common for RootNode {
     }
 }
 
+impl Cast for RootNode {
+    fn cast(element: SyntaxElement) -> Option<Self> {
+        Self::with_options(element, &Options::default())
+    }
+}
+
+impl RootNode {
+    /// Construct the DOM, with the semantic checks configured by the options.
+    pub fn with_options(element: SyntaxElement, options: &Options) -> Option<Self> {
+        if element.kind() != ROOT {
+            return None;
+        }
+        let syntax = element.into_node()?;
+
+        let mut errors = Vec::new();
+        let mut elements = Vec::new();
+        for sentence in syntax.children().filter(|n| n.kind() == SENTENCE) {
+            let entries = Entries::from_syntax(&sentence);
+            entries.check_duplicates(&mut errors);
+            for entry in &entries.0 {
+                check_value(entry.value(), &mut errors);
+            }
+
+            match ElementNode::from_entries(sentence.clone(), entries) {
+                Some(e) => elements.push(e),
+                None => errors.push(Error::InvalidType {
+                    range: sentence.text_range(),
+                }),
+            }
+        }
+        check::check_elements(&elements, options, &mut errors);
+
+        Some(Self {
+            syntax,
+            errors,
+            elements,
+        })
+    }
+}
+
+// duplicate keys in the tables nested in the value.
+fn check_value(value: &ValueNode, errors: &mut Vec<Error>) {
+    match value {
+        ValueNode::Table(t) => {
+            t.entries.check_duplicates(errors);
+            for entry in &t.entries.0 {
+                check_value(entry.value(), errors);
+            }
+        }
+        ValueNode::Array(a) => {
+            for item in &a.items {
+                check_value(item, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A sentence of the dump, either a vertex or an edge
+/// depending on its `type`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ElementNode {
+    Vertex(VertexNode),
+    Edge(EdgeNode),
+}
+
+impl ElementNode {
+    fn from_entries(syntax: SyntaxNode, entries: Entries) -> Option<Self> {
+        let kind = entries.get("type").and_then(|e| e.value().as_string())?;
+        match kind.as_str() {
+            "vertex" => Some(ElementNode::Vertex(VertexNode { syntax, entries })),
+            "edge" => Some(ElementNode::Edge(EdgeNode { syntax, entries })),
+            _ => None,
+        }
+    }
+
+    pub fn entries(&self) -> &Entries {
+        match self {
+            ElementNode::Vertex(v) => v.entries(),
+            ElementNode::Edge(v) => v.entries(),
+        }
+    }
+
+    pub fn id(&self) -> Option<&ValueNode> {
+        self.entries().get("id").map(EntryNode::value)
+    }
+
+    pub fn label(&self) -> Option<String> {
+        self.entries()
+            .get("label")
+            .and_then(|e| e.value().as_string())
+    }
+}
+
+impl Common for ElementNode {
+    fn syntax(&self) -> SyntaxElement {
+        match self {
+            ElementNode::Vertex(v) => v.syntax(),
+            ElementNode::Edge(v) => v.syntax(),
+        }
+    }
+
+    fn text_range(&self) -> TextRange {
+        match self {
+            ElementNode::Vertex(v) => v.text_range(),
+            ElementNode::Edge(v) => v.text_range(),
+        }
+    }
+}
+
+impl Cast for ElementNode {
+    fn cast(element: SyntaxElement) -> Option<Self> {
+        if element.kind() != SENTENCE {
+            return None;
+        }
+        let syntax = element.into_node()?;
+        let entries = Entries::from_syntax(&syntax);
+        Self::from_entries(syntax, entries)
+    }
+}
+
+dom_sentences!(
+    /// A sentence with `"type": "vertex"`.
+    VertexNode => Vertex,
+    /// A sentence with `"type": "edge"`.
+    EdgeNode => Edge
+);
+
+/// The key-value pairs of a sentence or a table, in source order.
+///
+/// Duplicate keys are kept, and reported as errors by the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Entries(Vec<EntryNode>);
+
+impl Entries {
+    /// Entries of a sentence are not wrapped in `ENTRY` nodes,
+    /// unlike the ones of a table.
+    fn from_syntax(syntax: &SyntaxNode) -> Self {
+        let mut entries = Vec::new();
+        let mut key = None;
+        for child in syntax.children() {
+            match child.kind() {
+                ENTRY => entries.extend(EntryNode::cast(child.into())),
+                KEY => key = KeyNode::cast(child.into()),
+                VALUE => {
+                    if let Some(key) = key.take() {
+                        entries.push(EntryNode {
+                            syntax: key.syntax(),
+                            value: ValueNode::from_value_node(child),
+                            key,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Entries(entries)
+    }
+
+    fn check_duplicates(&self, errors: &mut Vec<Error>) {
+        let tokens = self.0.iter().map(|e| e.key().token()).collect::<Vec<_>>();
+        let mut first_entries: HashMap<Cow<str>, &EntryNode> = HashMap::with_capacity(self.0.len());
+        for (entry, token) in self.0.iter().zip(&tokens) {
+            let name = token
+                .as_ref()
+                .map_or(Cow::Borrowed(""), |t| unquote(t.text()));
+            match first_entries.get(&name) {
+                Some(first) => errors.push(Error::DuplicatedKey {
+                    name: name.into_owned(),
+                    first: first.key().text_range(),
+                    second: entry.key().text_range(),
+                }),
+                None => {
+                    first_entries.insert(name, entry);
+                }
+            }
+        }
+    }
+
+    /// The first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&EntryNode> {
+        self.0.iter().find(|e| e.key().is(key))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, EntryNode> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl IntoIterator for Entries {
+    type Item = EntryNode;
+    type IntoIter = std::vec::IntoIter<EntryNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Entries {
+    type Item = &'a EntryNode;
+    type IntoIter = std::slice::Iter<'a, EntryNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// A key-value pair.
+///
+/// For the entries of sentences, which are not wrapped in
+/// a node, the syntax is the one of the key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryNode {
+    syntax: SyntaxElement,
+    key: KeyNode,
+    value: ValueNode,
+}
+
+impl EntryNode {
+    pub fn key(&self) -> &KeyNode {
+        &self.key
+    }
+
+    pub fn value(&self) -> &ValueNode {
+        &self.value
+    }
+
+    pub fn into_value(self) -> ValueNode {
+        self.value
+    }
+}
+
+impl Common for EntryNode {
+    fn syntax(&self) -> SyntaxElement {
+        self.syntax.clone()
+    }
+
+    fn text_range(&self) -> TextRange {
+        self.key.text_range().cover(self.value.text_range())
+    }
+
+    fn is_valid(&self) -> bool {
+        self.value.is_valid()
+    }
+}
+
+impl Cast for EntryNode {
+    fn cast(element: SyntaxElement) -> Option<Self> {
+        if element.kind() != ENTRY {
+            return None;
+        }
+        let syntax = element.into_node()?;
+        let key = syntax.children().find_map(|n| KeyNode::cast(n.into()))?;
+        let value = syntax
+            .children()
+            .find(|n| n.kind() == VALUE)
+            .map(ValueNode::from_value_node)?;
+
+        Some(Self {
+            syntax: syntax.into(),
+            key,
+            value,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyNode(SyntaxNode);
+
+impl KeyNode {
+    /// The key without quotes, with escapes resolved.
+    ///
+    /// Keys with invalid escape sequences are returned as they are.
+    pub fn name(&self) -> String {
+        self.token()
+            .map(|t| unquote(t.text()).into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Whether the key is the given name, without allocating
+    /// unless the key has escapes.
+    fn is(&self, name: &str) -> bool {
+        match self.token() {
+            Some(t) => unquote(t.text()) == name,
+            None => name.is_empty(),
+        }
+    }
+
+    /// The identifier or string token of the key, without the trivia
+    /// the parser attaches after it.
+    pub(crate) fn token(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+            .find(|t| matches!(t.kind(), IDENT | STRING))
+    }
+}
+
+impl Common for KeyNode {
+    fn syntax(&self) -> SyntaxElement {
+        self.0.clone().into()
+    }
+
+    fn text_range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+impl Cast for KeyNode {
+    fn cast(element: SyntaxElement) -> Option<Self> {
+        match element {
+            NodeOrToken::Node(n) if n.kind() == KEY => Some(Self(n)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ValueNode {
+    Bool(BoolNode),
+    String(StringNode),
+    Integer(IntegerNode),
+    Array(ArrayNode),
+    Table(TableNode),
+    /// Either an invalid token, or an empty `VALUE` node.
+    Invalid(SyntaxElement),
+}
+
+impl ValueNode {
+    fn from_value_node(value: SyntaxNode) -> Self {
+        value
+            .children_with_tokens()
+            .find_map(|c| match c.kind() {
+                WHITESPACE | COMMENT | NEWLINE => None,
+                _ => Some(Self::cast(c.clone()).unwrap_or(ValueNode::Invalid(c))),
+            })
+            .unwrap_or_else(|| ValueNode::Invalid(value.into()))
+    }
+
+    /// The value of an integer that fits in an `i64`.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            ValueNode::Integer(i) => i.value(),
+            _ => None,
+        }
+    }
+
+    /// The content of a string value.
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            ValueNode::String(s) => Some(s.content()),
+            _ => None,
+        }
+    }
+}
+
+impl Common for ValueNode {
+    fn syntax(&self) -> SyntaxElement {
+        match self {
+            ValueNode::Bool(v) => v.syntax(),
+            ValueNode::String(v) => v.syntax(),
+            ValueNode::Integer(v) => v.syntax(),
+            ValueNode::Array(v) => v.syntax(),
+            ValueNode::Table(v) => v.syntax(),
+            ValueNode::Invalid(v) => v.clone(),
+        }
+    }
+
+    fn text_range(&self) -> TextRange {
+        match self {
+            ValueNode::Bool(v) => v.text_range(),
+            ValueNode::String(v) => v.text_range(),
+            ValueNode::Integer(v) => v.text_range(),
+            ValueNode::Array(v) => v.text_range(),
+            ValueNode::Table(v) => v.text_range(),
+            ValueNode::Invalid(v) => v.text_range(),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        match self {
+            ValueNode::Array(v) => v.is_valid(),
+            ValueNode::Table(v) => v.is_valid(),
+            ValueNode::Invalid(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl Cast for ValueNode {
+    fn cast(element: SyntaxElement) -> Option<Self> {
+        match element.kind() {
+            BOOL => BoolNode::cast(element).map(ValueNode::Bool),
+            STRING => StringNode::cast(element).map(ValueNode::String),
+            INTEGER => IntegerNode::cast(element).map(ValueNode::Integer),
+            LIST => ArrayNode::cast(element).map(ValueNode::Array),
+            TABLE => TableNode::cast(element).map(ValueNode::Table),
+            VALUE => element.into_node().map(ValueNode::from_value_node),
+            _ => None,
+        }
+    }
+}
+
+/// An array value, e.g. the `inVs` of an edge.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArrayNode {
+    syntax: SyntaxNode,
+    items: Vec<ValueNode>,
+}
+
+impl ArrayNode {
+    pub fn items(&self) -> &[ValueNode] {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<ValueNode> {
+        self.items
+    }
+}
+
+impl Common for ArrayNode {
+    fn syntax(&self) -> SyntaxElement {
+        self.syntax.clone().into()
+    }
+
+    fn text_range(&self) -> TextRange {
+        trimmed_range(&self.syntax)
+    }
+
+    fn is_valid(&self) -> bool {
+        self.items.iter().all(Common::is_valid)
+    }
+}
+
+impl Cast for ArrayNode {
+    fn cast(element: SyntaxElement) -> Option<Self> {
+        if element.kind() != LIST {
+            return None;
+        }
+        let syntax = element.into_node()?;
+        let items = syntax
+            .children()
+            .filter(|n| n.kind() == VALUE)
+            .map(ValueNode::from_value_node)
+            .collect();
+
+        Some(Self { syntax, items })
+    }
+}
+
+/// A nested object value, e.g. the `start` of a range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableNode {
+    syntax: SyntaxNode,
+    entries: Entries,
+}
+
+impl TableNode {
+    pub fn entries(&self) -> &Entries {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Entries {
+        self.entries
+    }
+}
+
+impl Common for TableNode {
+    fn syntax(&self) -> SyntaxElement {
+        self.syntax.clone().into()
+    }
+
+    fn text_range(&self) -> TextRange {
+        trimmed_range(&self.syntax)
+    }
+
+    fn is_valid(&self) -> bool {
+        self.entries.iter().all(Common::is_valid)
+    }
+}
+
+impl Cast for TableNode {
+    fn cast(element: SyntaxElement) -> Option<Self> {
+        if element.kind() != TABLE {
+            return None;
+        }
+        let syntax = element.into_node()?;
+        let entries = Entries::from_syntax(&syntax);
+
+        Some(Self { syntax, entries })
+    }
+}
+
+dom_primitives!(
+    BOOL => BoolNode,
+    STRING => StringNode,
+    INTEGER => IntegerNode
+);
+
+impl StringNode {
+    /// The content of the string without quotes, with escapes resolved.
+    ///
+    /// Strings with invalid escape sequences are returned as they are,
+    /// those are reported as syntax errors.
+    pub fn content(&self) -> String {
+        unquote(self.0.text()).into_owned()
+    }
+}
+
+impl IntegerNode {
+    /// The text of the integer without its `_` separators.
+    pub fn digits(&self) -> String {
+        self.0.text().replace('_', "")
+    }
+
+    /// The value of the integer, if it fits in an `i64`.
+    pub fn value(&self) -> Option<i64> {
+        self.digits().parse().ok()
+    }
+}
+
+// the range of a list or a table, without the trivia
+// the parser attaches after the closing bracket.
+fn trimmed_range(syntax: &SyntaxNode) -> TextRange {
+    let end = syntax
+        .children_with_tokens()
+        .filter(|c| !matches!(c.kind(), WHITESPACE | COMMENT | NEWLINE))
+        .last()
+        .map(|c| c.text_range().end())
+        .unwrap_or_else(|| syntax.text_range().end());
+    TextRange::new(syntax.text_range().start(), end)
+}
+
+/// The semantic errors of a dump.
+///
+/// Every error carries the ranges of the sentences
+/// and values involved, in the order of the dump.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Error {
-    DuplicatedId { first: KeyNode, second: KeyNode },
-    ExpectedTableArray { target: KeyNode, key: KeyNode },
-    ExpectedTable { target: KeyNode, key: KeyNode },
-    InlineTable { target: KeyNode, key: KeyNode },
-    Spanned { range: TextRange, message: String },
+    /// A key is defined more than once in a sentence or a table.
+    DuplicatedKey {
+        name: String,
+        first: TextRange,
+        second: TextRange,
+    },
+    /// Two elements have the same `id`.
+    DuplicatedId {
+        id: String,
+        first: TextRange,
+        second: TextRange,
+    },
+    /// The `type` of a sentence is missing, or is neither `vertex` nor `edge`.
+    InvalidType {
+        range: TextRange,
+    },
+    /// An element lacks a property required by its label.
+    MissingProperty {
+        element: TextRange,
+        label: Option<String>,
+        property: String,
+    },
+    /// A property that is not in the schema of the label.
+    UnknownProperty {
+        range: TextRange,
+        label: String,
+        property: String,
+    },
+    /// A property with a JSON type the schema does not allow.
+    MistypedProperty {
+        range: TextRange,
+        property: String,
+        expected: &'static str,
+    },
+    /// The label is not one of the LSIF vertex or edge labels.
+    UnknownLabel {
+        range: TextRange,
+        label: String,
+    },
+    /// An edge references an id that is not the one of a vertex,
+    /// or in streaming mode, of a vertex emitted before the edge.
+    DanglingEdge {
+        edge: TextRange,
+        reference: TextRange,
+        missing_id: String,
+    },
+    /// An edge connects a vertex its label does not allow.
+    WrongEndpointType {
+        edge: TextRange,
+        endpoint: TextRange,
+        edge_label: String,
+        vertex_label: String,
+    },
+    /// The `start` of a range is after its `end`.
+    InvalidRange {
+        range: TextRange,
+        start: TextRange,
+        end: TextRange,
+    },
+    /// An end event that does not match the innermost event that began,
+    /// if any.
+    UnbalancedEvent {
+        range: TextRange,
+        begin: Option<TextRange>,
+    },
+    /// A begin event without an end event.
+    UnendedEvent {
+        begin: TextRange,
+    },
+    /// A range or a `contains` edge of a document after its end event.
+    ElementAfterEnd {
+        element: TextRange,
+        end: TextRange,
+    },
+    Spanned {
+        range: TextRange,
+        message: String,
+    },
     Generic(String),
 }
 
+impl Error {
+    /// The range to report the error at, if any.
+    pub fn text_range(&self) -> Option<TextRange> {
+        match self {
+            Error::DuplicatedKey { second, .. } => Some(*second),
+            Error::DuplicatedId { second, .. } => Some(*second),
+            Error::InvalidType { range } => Some(*range),
+            Error::MissingProperty { element, .. } => Some(*element),
+            Error::UnknownProperty { range, .. } => Some(*range),
+            Error::MistypedProperty { range, .. } => Some(*range),
+            Error::UnknownLabel { range, .. } => Some(*range),
+            Error::DanglingEdge { reference, .. } => Some(*reference),
+            Error::WrongEndpointType { endpoint, .. } => Some(*endpoint),
+            Error::InvalidRange { range, .. } => Some(*range),
+            Error::UnbalancedEvent { range, .. } => Some(*range),
+            Error::UnendedEvent { begin } => Some(*begin),
+            Error::ElementAfterEnd { element, .. } => Some(*element),
+            Error::Spanned { range, .. } => Some(*range),
+            Error::Generic(_) => None,
+        }
+    }
+}
+
 impl core::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::DuplicatedId { first, second } => write!(
+            Error::DuplicatedKey {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "duplicate key \"{}\", first defined at {:?} ({:?})",
+                name, first, second
+            ),
+            Error::DuplicatedId { id, first, second } => write!(
+                f,
+                "duplicate id {}, first defined at {:?} ({:?})",
+                id, first, second
+            ),
+            Error::InvalidType { range } => write!(
+                f,
+                "expected \"type\" to be \"vertex\" or \"edge\" ({:?})",
+                range
+            ),
+            Error::MissingProperty {
+                element,
+                label,
+                property,
+            } => match label {
+                Some(label) => write!(
+                    f,
+                    "missing property \"{}\" of \"{}\" ({:?})",
+                    property, label, element
+                ),
+                None => write!(f, "missing property \"{}\" ({:?})", property, element),
+            },
+            Error::UnknownProperty {
+                range,
+                label,
+                property,
+            } => write!(
+                f,
+                "unknown property \"{}\" of \"{}\" ({:?})",
+                property, label, range
+            ),
+            Error::MistypedProperty {
+                range,
+                property,
+                expected,
+            } => write!(
+                f,
+                "expected \"{}\" to be {} ({:?})",
+                property, expected, range
+            ),
+            Error::UnknownLabel { range, label } => {
+                write!(f, "unknown label \"{}\" ({:?})", label, range)
+            }
+            Error::DanglingEdge {
+                edge,
+                reference,
+                missing_id,
+            } => write!(
                 f,
-                "duplicate keys: \"{}\" ({:?}) and \"{}\" ({:?})",
-                &first.full_key_string(),
-                &first.text_range(),
-                &second.full_key_string(),
-                &second.text_range()
+                "edge at {:?} references the missing vertex {} ({:?})",
+                edge, missing_id, reference
             ),
-            Error::ExpectedTable { target, key } => write!(
+            Error::WrongEndpointType {
+                edge,
+                endpoint,
+                edge_label,
+                vertex_label,
+            } => write!(
                 f,
-                "Expected \"{}\" ({:?}) to be a table, but it is not, required by \"{}\" ({:?})",
-                &target.full_key_string(),
-                &target.text_range(),
-                &key.full_key_string(),
-                &key.text_range()
+                "\"{}\" edge at {:?} cannot connect a \"{}\" vertex ({:?})",
+                edge_label, edge, vertex_label, endpoint
             ),
-            Error::ExpectedTableArray { target, key } => write!(
+            Error::InvalidRange { range, start, end } => write!(
                 f,
-                "\"{}\" ({:?}) conflicts with array of tables: \"{}\" ({:?})",
-                &target.full_key_string(),
-                &target.text_range(),
-                &key.full_key_string(),
-                &key.text_range()
+                "range start at {:?} is after its end at {:?} ({:?})",
+                start, end, range
             ),
-            Error::InlineTable { target, key } => write!(
+            Error::UnbalancedEvent { range, begin } => match begin {
+                Some(begin) => write!(
+                    f,
+                    "end event does not match the event begun at {:?} ({:?})",
+                    begin, range
+                ),
+                None => write!(f, "end event without a begin event ({:?})", range),
+            },
+            Error::UnendedEvent { begin } => write!(f, "event is never ended ({:?})", begin),
+            Error::ElementAfterEnd { element, end } => write!(
                 f,
-                "inline tables cannot be modified: \"{}\" ({:?}), modification attempted here: \"{}\" ({:?})",
-                &target.full_key_string(),
-                &target.text_range(),
-                &key.full_key_string(),
-                &key.text_range()
+                "document content after its end event at {:?} ({:?})",
+                end, element
             ),
             Error::Spanned { range, message } => write!(f, "{} ({:?})", message, range),
             Error::Generic(s) => s.fmt(f),
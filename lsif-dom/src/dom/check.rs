@@ -0,0 +1,250 @@
+//! Semantic checks of the elements of a dump.
+
+use super::{Common, ElementNode, Error, Options, ValueNode};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+/// The vertex labels of LSIF, including the ones of older versions.
+pub(crate) const VERTEX_LABELS: &[&str] = &[
+    "metaData",
+    "$event",
+    "source",
+    "capabilities",
+    "project",
+    "document",
+    "range",
+    "resultSet",
+    "moniker",
+    "packageInformation",
+    "definitionResult",
+    "declarationResult",
+    "referenceResult",
+    "implementationResult",
+    "typeDefinitionResult",
+    "hoverResult",
+    "foldingRangeResult",
+    "documentLinkResult",
+    "documentSymbolResult",
+    "diagnosticResult",
+    "exportResult",
+    "externalImportResult",
+];
+
+/// The edge labels of LSIF, including the ones of older versions.
+pub(crate) const EDGE_LABELS: &[&str] = &[
+    "contains",
+    "item",
+    "next",
+    "moniker",
+    "nextMoniker",
+    "packageInformation",
+    "attach",
+    "refersTo",
+    "textDocument/definition",
+    "textDocument/declaration",
+    "textDocument/hover",
+    "textDocument/references",
+    "textDocument/implementation",
+    "textDocument/typeDefinition",
+    "textDocument/foldingRange",
+    "textDocument/documentLink",
+    "textDocument/documentSymbol",
+    "textDocument/diagnostic",
+];
+
+pub(crate) fn check_elements(elements: &[ElementNode], options: &Options, errors: &mut Vec<Error>) {
+    for element in elements {
+        check_element(element, errors);
+    }
+    check_ids(elements, options, errors);
+    check_references(elements, options, errors);
+    super::endpoints::check_endpoints(elements, options, errors);
+    super::schema::check_schemas(elements, options, errors);
+    super::events::check_events(elements, options, errors);
+}
+
+/// The properties of edges that reference vertices.
+pub(crate) const REFERENCE_KEYS: &[&str] = &["outV", "inV", "inVs", "document", "shard"];
+
+/// An element id, as compared by the checks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum IdKey {
+    Integer(i64),
+    /// An integer that doesn't fit in an `i64`, with its sign
+    /// and without leading zeros.
+    BigInteger(String),
+    String(String),
+}
+
+impl IdKey {
+    /// The key of an id value, `None` if it is neither
+    /// an integer nor a string.
+    ///
+    /// Unless the id types are distinct, strings of integers
+    /// have the same key as the integers.
+    pub(crate) fn new(value: &ValueNode, options: &Options) -> Option<Self> {
+        match value {
+            ValueNode::Integer(i) => IdKey::integer(&i.digits()),
+            ValueNode::String(s) => {
+                let content = s.content();
+                match IdKey::integer(&content) {
+                    Some(key) if !options.distinct_id_types => Some(key),
+                    _ => Some(IdKey::String(content)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The key of an integer, `None` if `digits` is not one.
+    fn integer(digits: &str) -> Option<Self> {
+        if let Ok(i) = digits.parse() {
+            return Some(IdKey::Integer(i));
+        }
+
+        let (sign, magnitude) = match digits.as_bytes().first() {
+            Some(b'-') => ("-", &digits[1..]),
+            Some(b'+') => ("", &digits[1..]),
+            _ => ("", digits),
+        };
+        if magnitude.is_empty() || !magnitude.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let magnitude = magnitude.trim_start_matches('0');
+        Some(IdKey::BigInteger(format!("{}{}", sign, magnitude)))
+    }
+}
+
+/// Every id must be unique across the dump, vertices and edges alike.
+fn check_ids(elements: &[ElementNode], options: &Options, errors: &mut Vec<Error>) {
+    let mut ids = HashMap::with_capacity(elements.len());
+    for element in elements {
+        let value = match element.id() {
+            Some(v) => v,
+            None => continue,
+        };
+        let key = match IdKey::new(value, options) {
+            Some(k) => k,
+            None => continue,
+        };
+
+        match ids.entry(key) {
+            Entry::Occupied(first) => errors.push(Error::DuplicatedId {
+                id: value.to_string(),
+                first: *first.get(),
+                second: value.text_range(),
+            }),
+            Entry::Vacant(v) => {
+                v.insert(value.text_range());
+            }
+        }
+    }
+}
+
+fn check_element(element: &ElementNode, errors: &mut Vec<Error>) {
+    let entries = element.entries();
+    let label = element.label();
+
+    for property in &["id", "label"] {
+        if entries.get(property).is_none() {
+            errors.push(Error::MissingProperty {
+                element: element.text_range(),
+                label: label.clone(),
+                property: property.to_string(),
+            });
+        }
+    }
+
+    if let Some(entry) = entries.get("label") {
+        let known = match element {
+            ElementNode::Vertex(_) => VERTEX_LABELS,
+            ElementNode::Edge(_) => EDGE_LABELS,
+        };
+        match &label {
+            Some(label) if known.contains(&label.as_str()) => {}
+            _ => errors.push(Error::UnknownLabel {
+                range: entry.value().text_range(),
+                label: label.clone().unwrap_or_else(|| entry.value().to_string()),
+            }),
+        }
+    }
+
+    if let (ElementNode::Vertex(_), Some("range")) = (element, label.as_deref()) {
+        check_range(element, errors);
+    }
+}
+
+fn check_range(element: &ElementNode, errors: &mut Vec<Error>) {
+    let entries = element.entries();
+    let (start, end) = match (entries.get("start"), entries.get("end")) {
+        (Some(start), Some(end)) => (start.value(), end.value()),
+        _ => return,
+    };
+
+    if let (Some(s), Some(e)) = (position(start), position(end)) {
+        if s > e {
+            errors.push(Error::InvalidRange {
+                range: element.text_range(),
+                start: start.text_range(),
+                end: end.text_range(),
+            });
+        }
+    }
+}
+
+/// The line and character of a position table.
+fn position(value: &ValueNode) -> Option<(i64, i64)> {
+    match value {
+        ValueNode::Table(t) => Some((
+            t.entries().get("line")?.value().as_integer()?,
+            t.entries().get("character")?.value().as_integer()?,
+        )),
+        _ => None,
+    }
+}
+
+/// Every vertex referenced by an edge must exist, and be emitted
+/// before the edge in streaming mode.
+fn check_references(elements: &[ElementNode], options: &Options, errors: &mut Vec<Error>) {
+    let mut vertices = HashSet::with_capacity(elements.len());
+    if !options.streaming {
+        vertices.extend(
+            elements
+                .iter()
+                .filter(|e| matches!(e, ElementNode::Vertex(_)))
+                .filter_map(|e| IdKey::new(e.id()?, options)),
+        );
+    }
+
+    for element in elements {
+        let edge = match element {
+            ElementNode::Vertex(v) => {
+                if options.streaming {
+                    vertices.extend(v.id().and_then(|id| IdKey::new(id, options)));
+                }
+                continue;
+            }
+            ElementNode::Edge(e) => e,
+        };
+
+        for key in REFERENCE_KEYS {
+            let value = match edge.entries().get(key) {
+                Some(entry) => entry.value(),
+                None => continue,
+            };
+            let references = match value {
+                ValueNode::Array(a) => a.items(),
+                v => std::slice::from_ref(v),
+            };
+            for reference in references {
+                match IdKey::new(reference, options) {
+                    Some(id) if !vertices.contains(&id) => errors.push(Error::DanglingEdge {
+                        edge: edge.text_range(),
+                        reference: reference.text_range(),
+                        missing_id: reference.to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! The vertex labels each edge label can connect.
+
+use super::{check::IdKey, Common, EdgeNode, ElementNode, Error, Options, ValueNode};
+use lsif_type::{Edge, EdgeData, Id, Item, ItemKind, MultiEdgeData};
+use std::{collections::HashMap, convert::TryFrom};
+
+/// Out labels, and the in labels they can lead to.
+type Rule = (&'static [&'static str], &'static [&'static str]);
+
+const RESULTS: &[&str] = &[
+    "definitionResult",
+    "declarationResult",
+    "referenceResult",
+    "implementationResult",
+    "typeDefinitionResult",
+];
+
+/// The endpoints allowed by the specification.
+fn rules(edge: &Edge) -> &'static [Rule] {
+    match edge {
+        Edge::Contains(_) => &[(&["project"], &["document"]), (&["document"], &["range"])],
+        Edge::Item(item) => match item.property {
+            Some(ItemKind::Definitions) | Some(ItemKind::References) => {
+                &[(&["referenceResult"], &["range"])]
+            }
+            Some(ItemKind::ReferenceResults) => &[(&["referenceResult"], &["referenceResult"])],
+            Some(ItemKind::ReferenceLinks) => &[(&["referenceResult"], &["moniker"])],
+            None => &[(RESULTS, &["range"])],
+        },
+        Edge::Next(_) => &[(&["range", "resultSet"], &["resultSet"])],
+        Edge::RefersTo(_) => &[(&["range"], &["resultSet"])],
+        Edge::Moniker(_) => &[(&["range", "resultSet"], &["moniker"])],
+        Edge::NextMoniker(_) | Edge::Attach(_) => &[(&["moniker"], &["moniker"])],
+        Edge::PackageInformation(_) => &[(&["moniker"], &["packageInformation"])],
+        Edge::Definition(_) => &[(&["range", "resultSet"], &["definitionResult"])],
+        Edge::Declaration(_) => &[(&["range", "resultSet"], &["declarationResult"])],
+        Edge::Hover(_) => &[(&["range", "resultSet"], &["hoverResult"])],
+        Edge::References(_) => &[(&["range", "resultSet"], &["referenceResult"])],
+        Edge::Implementation(_) => &[(&["range", "resultSet"], &["implementationResult"])],
+        Edge::TypeDefinition(_) => &[(&["range", "resultSet"], &["typeDefinitionResult"])],
+        Edge::FoldingRange(_) => &[(&["document"], &["foldingRangeResult"])],
+        Edge::DocumentLink(_) => &[(&["document"], &["documentLinkResult"])],
+        Edge::DocumentSymbol(_) => &[(&["document"], &["documentSymbolResult"])],
+        Edge::Diagnostic(_) => &[(&["project", "document"], &["diagnosticResult"])],
+    }
+}
+
+/// The typed edge of a DOM edge.
+///
+/// `None` if the edge is malformed, those are reported
+/// by the other checks.
+fn typed_edge(edge: &EdgeNode) -> Option<Edge> {
+    let entries = edge.entries();
+    let out_v = id(entries.get("outV")?.value())?;
+    let data = || {
+        Some(EdgeData {
+            in_v: id(entries.get("inV")?.value())?,
+            out_v: out_v.clone(),
+        })
+    };
+    let multi = || {
+        let in_vs = match entries.get("inVs")?.value() {
+            ValueNode::Array(a) => a.items().iter().map(id).collect(),
+            _ => None,
+        };
+        Some(MultiEdgeData {
+            in_vs: in_vs?,
+            out_v: out_v.clone(),
+        })
+    };
+
+    Some(match edge.label()?.as_str() {
+        "contains" => Edge::Contains(multi()?),
+        "item" => {
+            let shard = entries.get("shard").or_else(|| entries.get("document"))?;
+            let property = match entries.get("property") {
+                Some(p) => Some(match p.value().as_string()?.as_str() {
+                    "definitions" => ItemKind::Definitions,
+                    "references" => ItemKind::References,
+                    "referenceResults" => ItemKind::ReferenceResults,
+                    "referenceLinks" => ItemKind::ReferenceLinks,
+                    _ => return None,
+                }),
+                None => None,
+            };
+            Edge::Item(Item {
+                shard: id(shard.value())?,
+                property,
+                edge_data: multi()?,
+            })
+        }
+        "next" => Edge::Next(data()?),
+        "refersTo" => Edge::RefersTo(data()?),
+        "moniker" => Edge::Moniker(data()?),
+        "nextMoniker" => Edge::NextMoniker(data()?),
+        "packageInformation" => Edge::PackageInformation(data()?),
+        "attach" => Edge::Attach(data()?),
+        "textDocument/definition" => Edge::Definition(data()?),
+        "textDocument/declaration" => Edge::Declaration(data()?),
+        "textDocument/hover" => Edge::Hover(data()?),
+        "textDocument/references" => Edge::References(data()?),
+        "textDocument/implementation" => Edge::Implementation(data()?),
+        "textDocument/typeDefinition" => Edge::TypeDefinition(data()?),
+        "textDocument/foldingRange" => Edge::FoldingRange(data()?),
+        "textDocument/documentLink" => Edge::DocumentLink(data()?),
+        "textDocument/documentSymbol" => Edge::DocumentSymbol(data()?),
+        "textDocument/diagnostic" => Edge::Diagnostic(data()?),
+        _ => return None,
+    })
+}
+
+fn id(value: &ValueNode) -> Option<Id> {
+    match value {
+        ValueNode::Integer(i) => i.value().and_then(|i| u64::try_from(i).ok()).map(Id::from),
+        ValueNode::String(s) => Some(s.content().into()),
+        _ => None,
+    }
+}
+
+/// Every edge must connect vertices with the labels allowed by its own.
+pub(crate) fn check_endpoints(
+    elements: &[ElementNode],
+    options: &Options,
+    errors: &mut Vec<Error>,
+) {
+    let labels: HashMap<_, _> = elements
+        .iter()
+        .filter_map(|e| match e {
+            ElementNode::Vertex(v) => Some((IdKey::new(v.id()?, options)?, v.label()?)),
+            ElementNode::Edge(_) => None,
+        })
+        .collect();
+    let label_of = |value: &ValueNode| labels.get(&IdKey::new(value, options)?);
+
+    for edge in elements.iter().filter_map(|e| match e {
+        ElementNode::Edge(e) => Some(e),
+        ElementNode::Vertex(_) => None,
+    }) {
+        let rules = match typed_edge(edge) {
+            Some(typed) => rules(&typed),
+            None => continue,
+        };
+        let entries = edge.entries();
+        let edge_label = edge.label().unwrap_or_default();
+        let mut wrong = |endpoint: &ValueNode, vertex_label: &str| {
+            errors.push(Error::WrongEndpointType {
+                edge: edge.text_range(),
+                endpoint: endpoint.text_range(),
+                edge_label: edge_label.clone(),
+                vertex_label: vertex_label.to_string(),
+            })
+        };
+
+        // dangling references are reported on their own
+        let out_v = entries.get("outV").map(|e| e.value());
+        let out_label = match out_v.and_then(label_of) {
+            Some(l) => l,
+            None => continue,
+        };
+        let allowed = rules
+            .iter()
+            .filter(|(out, _)| out.contains(&out_label.as_str()))
+            .flat_map(|(_, in_)| in_.iter())
+            .collect::<Vec<_>>();
+        if allowed.is_empty() {
+            wrong(out_v.unwrap(), out_label);
+            continue;
+        }
+
+        let in_vs = match (entries.get("inV"), entries.get("inVs")) {
+            (Some(e), _) => std::slice::from_ref(e.value()),
+            (None, Some(e)) => match e.value() {
+                ValueNode::Array(a) => a.items(),
+                _ => continue,
+            },
+            (None, None) => continue,
+        };
+        for in_v in in_vs {
+            match label_of(in_v) {
+                Some(l) if !allowed.iter().any(|a| *a == l) => wrong(in_v, l),
+                _ => {}
+            }
+        }
+    }
+}
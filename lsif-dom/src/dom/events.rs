@@ -0,0 +1,143 @@
+//! The balance of the `$event` vertices.
+//!
+//! Events begin and end the content of projects and documents,
+//! and must be nested like brackets:
+//!
+//! ```text
+//! { id: 3, type: "vertex", label: "$event", kind: "begin", scope: "project", data: 1 }
+//! { id: 4, type: "vertex", label: "$event", kind: "begin", scope: "document", data: 2 }
+//! { id: 5, type: "vertex", label: "$event", kind: "end", scope: "document", data: 2 }
+//! { id: 6, type: "vertex", label: "$event", kind: "end", scope: "project", data: 1 }
+//! ```
+
+use super::{check::IdKey, Common, ElementNode, Error, Options, ValueNode, VertexNode};
+use rowan::TextRange;
+use std::collections::HashMap;
+
+/// An event that began, and is not ended yet.
+struct Open {
+    scope: String,
+    data: IdKey,
+    range: TextRange,
+}
+
+/// The events must be balanced, and nothing can be added
+/// to a document after its end event.
+pub(crate) fn check_events(elements: &[ElementNode], options: &Options, errors: &mut Vec<Error>) {
+    let mut open: Vec<Open> = Vec::new();
+    // the ended documents, with the index and range of their end event
+    let mut ended = HashMap::new();
+
+    for (index, element) in elements.iter().enumerate() {
+        let event = match element {
+            ElementNode::Vertex(v) if v.label().as_deref() == Some("$event") => v,
+            _ => continue,
+        };
+        let (kind, scope, data) = match event_data(event, options) {
+            Some(e) => e,
+            None => continue,
+        };
+        let range = event.text_range();
+
+        match kind.as_str() {
+            "begin" => open.push(Open { scope, data, range }),
+            "end" => {
+                let matching = open
+                    .iter()
+                    .rposition(|o| o.scope == scope && o.data == data);
+                match matching {
+                    Some(i) if i + 1 == open.len() => {}
+                    Some(_) => errors.push(Error::UnbalancedEvent {
+                        range,
+                        begin: open.last().map(|o| o.range),
+                    }),
+                    None => errors.push(Error::UnbalancedEvent { range, begin: None }),
+                }
+                if let Some(i) = matching {
+                    open.remove(i);
+                }
+                if scope == "document" {
+                    ended.entry(data).or_insert((index, range));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for o in open {
+        errors.push(Error::UnendedEvent { begin: o.range });
+    }
+
+    if !ended.is_empty() {
+        check_after_end(elements, options, &ended, errors);
+    }
+}
+
+/// The `kind`, `scope` and `data` of an event.
+fn event_data(event: &VertexNode, options: &Options) -> Option<(String, String, IdKey)> {
+    let entries = event.entries();
+    Some((
+        entries.get("kind")?.value().as_string()?,
+        entries.get("scope")?.value().as_string()?,
+        IdKey::new(entries.get("data")?.value(), options)?,
+    ))
+}
+
+/// Neither `contains` edges of ended documents, nor their ranges,
+/// can come after the end event.
+fn check_after_end(
+    elements: &[ElementNode],
+    options: &Options,
+    ended: &HashMap<IdKey, (usize, TextRange)>,
+    errors: &mut Vec<Error>,
+) {
+    let ranges: HashMap<_, _> = elements
+        .iter()
+        .enumerate()
+        .filter_map(|(index, e)| match e {
+            ElementNode::Vertex(v) if v.label().as_deref() == Some("range") => {
+                Some((IdKey::new(v.id()?, options)?, (index, v.text_range())))
+            }
+            _ => None,
+        })
+        .collect();
+
+    for (index, element) in elements.iter().enumerate() {
+        let edge = match element {
+            ElementNode::Edge(e) if e.label().as_deref() == Some("contains") => e,
+            _ => continue,
+        };
+        let entries = edge.entries();
+        let (end_index, end) = match entries
+            .get("outV")
+            .and_then(|e| IdKey::new(e.value(), options))
+            .and_then(|id| ended.get(&id))
+        {
+            Some(e) => *e,
+            None => continue,
+        };
+
+        if index > end_index {
+            errors.push(Error::ElementAfterEnd {
+                element: edge.text_range(),
+                end,
+            });
+        }
+
+        let in_vs = match entries.get("inVs").map(|e| e.value()) {
+            Some(ValueNode::Array(a)) => a.items(),
+            _ => continue,
+        };
+        for in_v in in_vs {
+            match IdKey::new(in_v, options).and_then(|id| ranges.get(&id)) {
+                Some((range_index, range)) if *range_index > end_index => {
+                    errors.push(Error::ElementAfterEnd {
+                        element: *range,
+                        end,
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+}
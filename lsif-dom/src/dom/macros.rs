@@ -73,4 +73,54 @@ macro_rules! dom_sealed {
     ($($id:ty),*) => {
         $(impl Sealed for $id {})*
     };
-}
\ No newline at end of file
+}
+/// Nodes of sentences, that only differ by their `type`.
+macro_rules! dom_sentences {
+    ($($(#[$attr:meta])* $ast:ident => $variant:ident),*) => {
+        $(
+            $(#[$attr])*
+            #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+            pub struct $ast {
+                syntax: SyntaxNode,
+                entries: Entries,
+            }
+
+            impl $ast {
+                pub fn entries(&self) -> &Entries {
+                    &self.entries
+                }
+
+                pub fn into_entries(self) -> Entries {
+                    self.entries
+                }
+
+                pub fn id(&self) -> Option<&ValueNode> {
+                    self.entries.get("id").map(EntryNode::value)
+                }
+
+                pub fn label(&self) -> Option<String> {
+                    self.entries.get("label").and_then(|e| e.value().as_string())
+                }
+            }
+
+            impl Common for $ast {
+                fn syntax(&self) -> SyntaxElement {
+                    self.syntax.clone().into()
+                }
+
+                fn text_range(&self) -> TextRange {
+                    self.syntax.text_range()
+                }
+            }
+
+            impl Cast for $ast {
+                fn cast(element: SyntaxElement) -> Option<Self> {
+                    match ElementNode::cast(element)? {
+                        ElementNode::$variant(v) => Some(v),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
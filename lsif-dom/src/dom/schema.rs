@@ -0,0 +1,334 @@
+//! The properties of each vertex and edge label.
+//!
+//! The `id`, `type` and `label` of elements are checked
+//! on their own, the schemas only list the other properties.
+//!
+//! The schemas follow the model of `lsif_type`: a property is required
+//! if a dump of its version cannot be read without it. Dumps are checked
+//! against the version of their `metaData` vertex, or the current one.
+
+use super::{Common, ElementNode, Entries, Error, Options, ValueNode};
+use lsif_type::version::Version;
+use rowan::TextRange;
+
+/// The JSON type of a property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Bool,
+    Integer,
+    String,
+    Array,
+    Table,
+    /// An integer or a string.
+    Id,
+    /// An array of ids.
+    Ids,
+    /// A table with `line` and `character` integers.
+    Position,
+    Any,
+}
+
+impl Type {
+    fn name(self) -> &'static str {
+        match self {
+            Type::Bool => "a boolean",
+            Type::Integer => "an integer",
+            Type::String => "a string",
+            Type::Array => "an array",
+            Type::Table => "an object",
+            Type::Id => "an id",
+            Type::Ids => "an array of ids",
+            Type::Position => "a position",
+            Type::Any => "a value",
+        }
+    }
+
+    fn matches(self, value: &ValueNode) -> bool {
+        match (self, value) {
+            (Type::Any, _)
+            | (Type::Bool, ValueNode::Bool(_))
+            | (Type::Integer, ValueNode::Integer(_))
+            | (Type::String, ValueNode::String(_))
+            | (Type::Array, ValueNode::Array(_))
+            | (Type::Table, ValueNode::Table(_))
+            | (Type::Position, ValueNode::Table(_))
+            | (Type::Id, ValueNode::Integer(_))
+            | (Type::Id, ValueNode::String(_)) => true,
+            (Type::Ids, ValueNode::Array(a)) => a.items().iter().all(|i| Type::Id.matches(i)),
+            _ => false,
+        }
+    }
+}
+
+/// A property, under any of its names.
+#[derive(Debug, Clone, Copy)]
+struct Property {
+    names: &'static [&'static str],
+    ty: Type,
+    /// The first version that requires the property.
+    required_since: Option<Version>,
+}
+
+const fn req(names: &'static [&'static str], ty: Type) -> Property {
+    req_since(Version::V0_4, names, ty)
+}
+
+const fn req_since(version: Version, names: &'static [&'static str], ty: Type) -> Property {
+    Property {
+        names,
+        ty,
+        required_since: Some(version),
+    }
+}
+
+const fn opt(names: &'static [&'static str], ty: Type) -> Property {
+    Property {
+        names,
+        ty,
+        required_since: None,
+    }
+}
+
+const COMMON: &[&str] = &["id", "type", "label"];
+
+const POSITION: &[Property] = &[
+    req(&["line"], Type::Integer),
+    req(&["character"], Type::Integer),
+];
+
+const EDGE: &[Property] = &[req(&["outV"], Type::Id), req(&["inV"], Type::Id)];
+
+const MULTI_EDGE: &[Property] = &[
+    req(&["outV"], Type::Id),
+    req(&["inVs"], Type::Ids),
+    opt(&["shard", "document"], Type::Id),
+];
+
+const RESULT: &[Property] = &[opt(&["result"], Type::Any)];
+
+const RESULT_ARRAY: &[Property] = &[req(&["result"], Type::Array)];
+
+const META_DATA: &[Property] = &[
+    req(&["version"], Type::String),
+    // older dumps are upgraded to `utf-16`
+    req_since(Version::V0_5, &["positionEncoding"], Type::String),
+    opt(&["projectRoot"], Type::String),
+    opt(&["toolInfo"], Type::Table),
+];
+
+const EVENT: &[Property] = &[
+    req(&["kind"], Type::String),
+    req(&["scope"], Type::String),
+    req(&["data"], Type::Id),
+];
+
+const SOURCE: &[Property] = &[
+    req(&["workspaceRoot"], Type::String),
+    opt(&["repository"], Type::Table),
+];
+
+const CAPABILITIES: &[Property] = &[
+    opt(&["hoverProvider"], Type::Bool),
+    opt(&["declarationProvider"], Type::Bool),
+    opt(&["definitionProvider"], Type::Bool),
+    opt(&["typeDefinitionProvider"], Type::Bool),
+    opt(&["referencesProvider"], Type::Bool),
+    opt(&["documentSymbolProvider"], Type::Bool),
+    opt(&["foldingRangeProvider"], Type::Bool),
+    opt(&["diagnosticProvider"], Type::Bool),
+];
+
+const PROJECT: &[Property] = &[
+    req(&["kind"], Type::String),
+    opt(&["name"], Type::String),
+    opt(&["resource"], Type::String),
+    opt(&["contents"], Type::String),
+];
+
+const DOCUMENT: &[Property] = &[
+    req(&["uri"], Type::String),
+    req(&["languageId"], Type::String),
+    opt(&["contents"], Type::String),
+];
+
+const RANGE: &[Property] = &[
+    req(&["start"], Type::Position),
+    req(&["end"], Type::Position),
+    opt(&["tag"], Type::Table),
+];
+
+const MONIKER: &[Property] = &[
+    req(&["scheme"], Type::String),
+    req(&["identifier"], Type::String),
+    req(&["unique"], Type::String),
+    opt(&["kind"], Type::String),
+];
+
+const PACKAGE_INFORMATION: &[Property] = &[
+    req(&["name"], Type::String),
+    req(&["manager"], Type::String),
+    opt(&["version"], Type::String),
+    opt(&["uri"], Type::String),
+    opt(&["contents"], Type::String),
+    opt(&["repository"], Type::Table),
+];
+
+const ITEM: &[Property] = &[
+    req(&["outV"], Type::Id),
+    req(&["inVs"], Type::Ids),
+    // the document of 0.4 items can be the one containing their ranges
+    req_since(Version::V0_5, &["shard", "document"], Type::Id),
+    opt(&["property"], Type::String),
+];
+
+const RESULT_SET: &[Property] = &[opt(&["key"], Type::String)];
+
+const HOVER_RESULT: &[Property] = &[req(&["result"], Type::Table)];
+
+fn vertex_schema(label: &str) -> Option<&'static [Property]> {
+    Some(match label {
+        "metaData" => META_DATA,
+        "$event" => EVENT,
+        "source" => SOURCE,
+        "capabilities" => CAPABILITIES,
+        "project" => PROJECT,
+        "document" => DOCUMENT,
+        "range" => RANGE,
+        "resultSet" => RESULT_SET,
+        "moniker" => MONIKER,
+        "packageInformation" => PACKAGE_INFORMATION,
+        "definitionResult"
+        | "declarationResult"
+        | "referenceResult"
+        | "implementationResult"
+        | "typeDefinitionResult"
+        | "exportResult"
+        | "externalImportResult" => RESULT,
+        "hoverResult" => HOVER_RESULT,
+        "foldingRangeResult"
+        | "documentLinkResult"
+        | "documentSymbolResult"
+        | "diagnosticResult" => RESULT_ARRAY,
+        _ => return None,
+    })
+}
+
+fn edge_schema(label: &str) -> Option<&'static [Property]> {
+    Some(match label {
+        "contains" => MULTI_EDGE,
+        "item" => ITEM,
+        "next"
+        | "moniker"
+        | "nextMoniker"
+        | "packageInformation"
+        | "attach"
+        | "refersTo"
+        | "textDocument/definition"
+        | "textDocument/declaration"
+        | "textDocument/hover"
+        | "textDocument/references"
+        | "textDocument/implementation"
+        | "textDocument/typeDefinition"
+        | "textDocument/foldingRange"
+        | "textDocument/documentLink"
+        | "textDocument/documentSymbol"
+        | "textDocument/diagnostic" => EDGE,
+        _ => return None,
+    })
+}
+
+/// The properties of every element must follow the schema of its label.
+///
+/// Elements with unknown labels are reported on their own.
+pub(crate) fn check_schemas(elements: &[ElementNode], options: &Options, errors: &mut Vec<Error>) {
+    let version = version(elements).unwrap_or(Version::CURRENT);
+    for element in elements {
+        let label = match element.label() {
+            Some(l) => l,
+            None => continue,
+        };
+        let schema = match element {
+            ElementNode::Vertex(_) => vertex_schema(&label),
+            ElementNode::Edge(_) => edge_schema(&label),
+        };
+        if let Some(schema) = schema {
+            let mut checker = Checker {
+                label: &label,
+                version,
+                options,
+                errors: &mut *errors,
+            };
+            checker.check_entries(element.text_range(), element.entries(), schema, COMMON);
+        }
+    }
+}
+
+/// The version of the `metaData` vertex, if it is the first element.
+fn version(elements: &[ElementNode]) -> Option<Version> {
+    let meta_data = match elements.first()? {
+        e @ ElementNode::Vertex(_) if e.label().as_deref() == Some("metaData") => e,
+        _ => return None,
+    };
+    let version = meta_data.entries().get("version")?.value().as_string()?;
+    version.parse().ok()
+}
+
+struct Checker<'a> {
+    label: &'a str,
+    version: Version,
+    options: &'a Options,
+    errors: &'a mut Vec<Error>,
+}
+
+impl Checker<'_> {
+    fn check_entries(
+        &mut self,
+        range: TextRange,
+        entries: &Entries,
+        schema: &[Property],
+        ignored: &[&str],
+    ) {
+        for property in schema {
+            let entry = property.names.iter().find_map(|n| entries.get(n));
+            let value = match entry {
+                Some(e) => e.value(),
+                None => {
+                    if property.required_since.is_some_and(|v| v <= self.version) {
+                        self.errors.push(Error::MissingProperty {
+                            element: range,
+                            label: Some(self.label.to_string()),
+                            property: property.names[0].to_string(),
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            if !property.ty.matches(value) {
+                self.errors.push(Error::MistypedProperty {
+                    range: value.text_range(),
+                    property: entry.unwrap().key().name(),
+                    expected: property.ty.name(),
+                });
+            } else if let (Type::Position, ValueNode::Table(t)) = (property.ty, value) {
+                self.check_entries(t.text_range(), t.entries(), POSITION, &[]);
+            }
+        }
+
+        if self.options.allow_unknown_properties {
+            return;
+        }
+        for entry in entries {
+            let name = entry.key().name();
+            let known = ignored.contains(&name.as_str())
+                || schema.iter().any(|p| p.names.contains(&name.as_str()));
+            if !known {
+                self.errors.push(Error::UnknownProperty {
+                    range: entry.key().text_range(),
+                    label: self.label.to_string(),
+                    property: name,
+                });
+            }
+        }
+    }
+}
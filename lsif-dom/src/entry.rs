@@ -0,0 +1,416 @@
+//! This module is used to convert the elements of the DOM
+//! into the typed entries of `lsif-type`.
+//!
+//! The elements are converted into [`Value`]s, which are deserialized
+//! into [`Entry`]s. When an element does not have the shape of an entry,
+//! the error points at the property whose value was rejected, or at the
+//! element if a property is missing.
+//!
+//! serde buffers flattened and internally tagged data before deserializing
+//! it, so the deserializer cannot know the path of a rejected value. The
+//! path is found afterwards instead: of the values equal to the rejected
+//! one, it is the first whose removal changes the error.
+
+use crate::{
+    dom::{self, Common, ElementNode, Entries, Options, RootNode, ValueNode},
+    value::{Map, Value},
+    IntoDom,
+};
+use lsif_parser_lib::parser::Parse;
+use lsif_type::Entry;
+use rowan::TextRange;
+use serde::de::{
+    self,
+    value::{MapDeserializer, SeqDeserializer, StringDeserializer},
+    Deserialize, IntoDeserializer, Unexpected, Visitor,
+};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+};
+
+pub type Error = dom::Error;
+
+impl TryFrom<ElementNode> for Entry {
+    type Error = Error;
+
+    fn try_from(node: ElementNode) -> Result<Self, Self::Error> {
+        let value: Value = node.clone().try_into()?;
+        Entry::deserialize(value).map_err(|e| e.locate(&node))
+    }
+}
+
+/// Parse the elements of a dump into entries.
+///
+/// The errors are the syntax errors, the errors of the DOM, then the ones
+/// of the elements that are not entries. Elements that already have
+/// errors of the DOM are left out without further errors.
+pub fn from_parse(parse: Parse) -> (Vec<Entry>, Vec<Error>) {
+    from_parse_with(parse, &Options::default())
+}
+
+/// Same as [`from_parse`], with the checks of the DOM configured by the options.
+pub fn from_parse_with(parse: Parse, options: &Options) -> (Vec<Entry>, Vec<Error>) {
+    let mut errors: Vec<Error> = parse
+        .errors
+        .iter()
+        .map(|e| Error::Spanned {
+            range: e.range,
+            message: e.message.clone(),
+        })
+        .collect();
+    let root = parse.into_dom_with(options);
+    errors.extend(root.errors().iter().cloned());
+
+    let entries = from_root(root, &mut errors);
+    (entries, errors)
+}
+
+fn from_root(root: RootNode, errors: &mut Vec<Error>) -> Vec<Entry> {
+    let mut reported: Vec<TextRange> = errors.iter().filter_map(Error::text_range).collect();
+    reported.sort_by_key(|r| r.start());
+
+    let mut entries = Vec::with_capacity(root.elements().len());
+    for element in root.into_elements() {
+        let range = element.text_range();
+        // the reported ranges that start in the element
+        let first = reported.partition_point(|r| r.start() < range.start());
+        let last = reported.partition_point(|r| r.start() <= range.end());
+        match Entry::try_from(element) {
+            Ok(entry) => entries.push(entry),
+            Err(_)
+                if reported[first..last]
+                    .iter()
+                    .any(|r| range.contains_range(*r)) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+    entries
+}
+
+/// The error of the deserialization of a [`Value`],
+/// which is located in its element afterwards.
+#[derive(Debug)]
+pub struct DeserializeError {
+    message: String,
+    missing: Option<&'static str>,
+    found: Found,
+}
+
+/// The rejected value, to find it in the element.
+#[derive(Debug)]
+enum Found {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    String(String),
+    Array,
+    Table,
+    Unknown,
+}
+
+impl From<Unexpected<'_>> for Found {
+    fn from(unexpected: Unexpected<'_>) -> Self {
+        match unexpected {
+            Unexpected::Unit | Unexpected::Option => Found::Null,
+            Unexpected::Bool(b) => Found::Bool(b),
+            Unexpected::Unsigned(u) => Found::Integer(u.into()),
+            Unexpected::Signed(i) => Found::Integer(i.into()),
+            Unexpected::Str(s) => Found::String(s.into()),
+            Unexpected::Seq => Found::Array,
+            Unexpected::Map => Found::Table,
+            _ => Found::Unknown,
+        }
+    }
+}
+
+impl Found {
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Found::Null, Value::Null) => true,
+            (Found::Bool(b), Value::Bool(v)) => b == v,
+            (Found::Integer(i), Value::Integer(v)) => *i == i128::from(*v),
+            (Found::Integer(i), Value::UnsignedInteger(v)) => *i == i128::from(*v),
+            (Found::String(s), Value::String(v)) => s == v,
+            (Found::Array, Value::Array(_)) | (Found::Table, Value::Map(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A step from a value to one of its children.
+#[derive(Debug, Clone)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+impl DeserializeError {
+    fn new(message: String, found: Found) -> Self {
+        DeserializeError {
+            message,
+            missing: None,
+            found,
+        }
+    }
+
+    /// The error at the rejected value in the element,
+    /// or at the element if it cannot be found.
+    fn locate(self, element: &ElementNode) -> Error {
+        if let Some(property) = self.missing {
+            return Error::MissingProperty {
+                element: element.text_range(),
+                label: element.label(),
+                property: property.into(),
+            };
+        }
+        let located = Value::try_from(element.clone())
+            .ok()
+            .and_then(|value| self.path(value))
+            .and_then(|path| Some((find(element.entries(), &path)?, path)));
+        match located {
+            Some((range, path)) => Error::Spanned {
+                range,
+                message: format!("invalid \"{}\": {}", display(&path), self.message),
+            },
+            None => Error::Spanned {
+                range: element.text_range(),
+                message: self.message,
+            },
+        }
+    }
+
+    /// The path of the rejected value in the value of the element: of the
+    /// values equal to it, the first one whose removal changes the error.
+    fn path(&self, value: Value) -> Option<Vec<Step>> {
+        let mut candidates = Vec::new();
+        self.collect(&value, &mut Vec::new(), &mut candidates);
+        candidates.into_iter().find(|path| {
+            let mut probe = value.clone();
+            remove(&mut probe, path);
+            match Entry::deserialize(probe) {
+                Ok(_) => true,
+                Err(e) => e.message != self.message,
+            }
+        })
+    }
+
+    fn collect(&self, value: &Value, path: &mut Vec<Step>, candidates: &mut Vec<Vec<Step>>) {
+        let children: Vec<(Step, &Value)> = match value {
+            Value::Map(m) => m.iter().map(|(k, v)| (Step::Key(k.clone()), v)).collect(),
+            Value::Array(a) => a
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (Step::Index(i), v))
+                .collect(),
+            _ => return,
+        };
+        for (step, child) in children {
+            path.push(step);
+            if self.found.matches(child) {
+                candidates.push(path.clone());
+            }
+            self.collect(child, path, candidates);
+            path.pop();
+        }
+    }
+}
+
+fn remove(value: &mut Value, path: &[Step]) {
+    match (value, path) {
+        (Value::Map(m), [Step::Key(k)]) => {
+            m.shift_remove(k);
+        }
+        (Value::Array(a), [Step::Index(i)]) => {
+            a.remove(*i);
+        }
+        (Value::Map(m), [Step::Key(k), rest @ ..]) => {
+            if let Some(v) = m.get_mut(k) {
+                remove(v, rest)
+            }
+        }
+        (Value::Array(a), [Step::Index(i), rest @ ..]) => {
+            if let Some(v) = a.get_mut(*i) {
+                remove(v, rest)
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The range of the value at the path.
+fn find(entries: &Entries, path: &[Step]) -> Option<TextRange> {
+    match path.split_first()? {
+        (Step::Key(k), rest) => find_in_value(entries.get(k)?.value(), rest),
+        (Step::Index(_), _) => None,
+    }
+}
+
+fn find_in_value(value: &ValueNode, path: &[Step]) -> Option<TextRange> {
+    match (value, path.first()) {
+        (_, None) => Some(value.text_range()),
+        (ValueNode::Table(t), Some(Step::Key(_))) => find(t.entries(), path),
+        (ValueNode::Array(a), Some(Step::Index(i))) => {
+            find_in_value(a.items().get(*i)?, &path[1..])
+        }
+        _ => None,
+    }
+}
+
+/// The path as in `start.line` or `inVs[0]`.
+fn display(path: &[Step]) -> String {
+    let mut s = String::new();
+    for step in path {
+        match step {
+            Step::Key(k) if s.is_empty() => s.push_str(k),
+            Step::Key(k) => {
+                s.push('.');
+                s.push_str(k);
+            }
+            Step::Index(i) => s.push_str(&format!("[{}]", i)),
+        }
+    }
+    s
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeserializeError::new(msg.to_string(), Found::Unknown)
+    }
+
+    fn invalid_type(unexp: Unexpected<'_>, exp: &dyn de::Expected) -> Self {
+        let message = format!("invalid type: {}, expected {}", unexp, exp);
+        DeserializeError::new(message, unexp.into())
+    }
+
+    fn invalid_value(unexp: Unexpected<'_>, exp: &dyn de::Expected) -> Self {
+        let message = format!("invalid value: {}, expected {}", unexp, exp);
+        DeserializeError::new(message, unexp.into())
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        let message = format!(
+            "unknown variant `{}`, expected one of {}",
+            variant,
+            expected.join(", ")
+        );
+        DeserializeError::new(message, Found::String(variant.into()))
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        DeserializeError {
+            message: format!("missing field `{}`", field),
+            missing: Some(field),
+            found: Found::Unknown,
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeserializeError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Integer(v) => visitor.visit_i64(v),
+            Value::UnsignedInteger(v) => visitor.visit_u64(v),
+            Value::Float(v) => visitor.visit_f64(v),
+            Value::String(v) => visitor.visit_string(v),
+            Value::Array(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            Value::Map(v) => visitor.visit_map(MapDeserializer::new(v.into_iter())),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    /// Unit variants are strings, the other variants
+    /// are tables with the variant as only key.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self {
+            Value::String(v) => visitor.visit_enum(StringDeserializer::new(v)),
+            Value::Map(v) if v.len() == 1 => visitor.visit_enum(EnumDeserializer(v)),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// A table with a single key, which is the variant.
+struct EnumDeserializer(Map);
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = DeserializeError;
+    type Variant = Value;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Value), Self::Error> {
+        let (variant, value) = self.0.into_iter().next().unwrap();
+        let variant = seed.deserialize(StringDeserializer::<DeserializeError>::new(variant))?;
+        Ok((variant, value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Value {
+    type Error = DeserializeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -1,10 +1,27 @@
 pub mod dom;
-mod value;
+pub mod entry;
+pub mod value;
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+use lsif_parser_lib::parser::Parse;
+
+/// Turn the parse results into a DOM tree.
+pub trait IntoDom: Sized {
+    /// Any semantic errors that occur will be collected
+    /// in the returned DOM node.
+    fn into_dom(self) -> dom::RootNode {
+        self.into_dom_with(&dom::Options::default())
+    }
+
+    /// Same as [`into_dom`](IntoDom::into_dom), with
+    /// the semantic checks configured by the options.
+    fn into_dom_with(self, options: &dom::Options) -> dom::RootNode;
+}
+
+impl IntoDom for Parse {
+    fn into_dom_with(self, options: &dom::Options) -> dom::RootNode {
+        dom::RootNode::with_options(rowan::NodeOrToken::Node(self.into_syntax()), options).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,488 @@
+use crate::{
+    dom::{Cast, Common, ElementNode, Error, Node, Options, RootNode, ValueNode},
+    entry,
+    value::Value,
+    IntoDom,
+};
+use lsif_parser_lib::parser;
+use lsif_type::Id;
+use rowan::{TextRange, TextSize};
+use std::convert::TryFrom;
+
+#[test]
+fn test_dom() {
+    let src = "{\"id\":1,\"type\":\"vertex\",\"label\":\"range\",\"start\":{\"line\":0,\"character\":9},\"end\":{\"line\":0,\"character\":12}}\n\
+               { id: 2, type: \"edge\", label: \"contains\", outV: 3, inVs: [4, 5] }\n\
+               { id: 3, type: \"vertex\", label: \"document\", uri: \"file:///a.rs\", languageId: \"rust\" }\n\
+               { id: 4, type: \"vertex\", label: \"range\", start: { line: 1, character: 0 }, end: { line: 1, character: 2 } }\n\
+               { id: 5, type: \"vertex\", label: \"range\", start: { line: 2, character: 0 }, end: { line: 2, character: 2 } }";
+    let root = parser::parse(src).into_dom();
+    assert!(root.errors().is_empty(), "{:?}", root.errors());
+    assert_eq!(root.elements().len(), 5);
+
+    let vertex = root.vertices().next().unwrap();
+    assert_eq!(vertex.label().as_deref(), Some("range"));
+    assert_eq!(vertex.id().map(|v| v.to_string()).as_deref(), Some("1"));
+    let start = match vertex.entries().get("start").unwrap().value() {
+        ValueNode::Table(t) => t,
+        v => panic!("expected a table, got {:?}", v),
+    };
+    assert_eq!(start.entries().len(), 2);
+    assert_eq!(
+        start
+            .entries()
+            .get("character")
+            .unwrap()
+            .value()
+            .to_string(),
+        "9"
+    );
+
+    let edge = root.edges().next().unwrap();
+    assert_eq!(edge.label().as_deref(), Some("contains"));
+    let in_vs = edge.entries().get("inVs").unwrap();
+    assert_eq!(&src[in_vs.text_range()], "inVs: [4, 5]");
+    match in_vs.value() {
+        ValueNode::Array(a) => assert_eq!(
+            a.items().iter().map(|i| i.to_string()).collect::<Vec<_>>(),
+            vec!["4", "5"]
+        ),
+        v => panic!("expected an array, got {:?}", v),
+    }
+
+    let sentence = parser::parse(src).into_syntax().children().nth(1).unwrap();
+    match Node::cast(sentence.into()) {
+        Some(Node::Edge(e)) => assert_eq!(e.entries().len(), 5),
+        n => panic!("expected an edge, got {:?}", n),
+    }
+
+    // whitespace before the colon is not part of the key
+    let root = parser::parse("{ id : 1, type : \"vertex\", \"label\" : \"resultSet\" }").into_dom();
+    assert!(root.errors().is_empty(), "{:?}", root.errors());
+    let vertex = root.vertices().next().unwrap();
+    assert_eq!(vertex.label().as_deref(), Some("resultSet"));
+    assert_eq!(vertex.id().map(|v| v.to_string()).as_deref(), Some("1"));
+    assert!(vertex.entries().get("type").is_some());
+    let value = Value::try_from(root).unwrap();
+    assert_eq!(
+        value.as_array().unwrap()[0]
+            .as_map()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>(),
+        vec!["id", "type", "label"]
+    );
+}
+
+#[test]
+fn test_dom_errors() {
+    let src = "{ id: 1, type: \"vertex\", label: \"a\", label: \"b\" }\n\
+               { id: 2, type: \"other\" }\n\
+               { id: 3, type: \"vertex\", x: [{ a: 1, a: 2 }] }";
+    let root = parser::parse(src).into_dom();
+    assert_eq!(root.elements().len(), 2);
+    assert!(matches!(root.elements()[1], ElementNode::Vertex(_)));
+
+    let errors = root
+        .errors()
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "duplicate key \"label\", first defined at 25..30 (37..42)",
+            "expected \"type\" to be \"vertex\" or \"edge\" (50..74)",
+            "duplicate key \"a\", first defined at 106..107 (112..113)",
+            "unknown label \"a\" (32..35)",
+            "missing property \"label\" (75..121)",
+        ]
+    );
+
+    // keys are compared after their escapes are resolved
+    let src = r#"{ id: 1, "\u0069d": 2, type: "vertex", label: "project", "id": 3 }"#;
+    let root = parser::parse(src).into_dom();
+    let duplicates = root
+        .errors()
+        .iter()
+        .filter_map(|e| match e {
+            Error::DuplicatedKey {
+                name,
+                first,
+                second,
+            } => Some((name.as_str(), &src[*first], &src[*second])),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        duplicates,
+        vec![("id", "id", r#""\u0069d""#), ("id", "id", r#""id""#)]
+    );
+}
+
+#[test]
+fn test_semantic_errors() {
+    let src = "{ id: 1, type: \"vertex\", label: \"range\", start: { line: 2, character: 0 }, end: { line: 1, character: 4 } }\n\
+               { id: 2, type: \"edge\", label: \"range\" }\n\
+               { type: \"vertex\", label: \"resultSet\" }";
+    let root = parser::parse(src).into_dom();
+    assert_eq!(root.elements().len(), 3);
+
+    let errors = root.errors();
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    match &errors[0] {
+        Error::InvalidRange { start, end, .. } => {
+            assert_eq!(&src[*start], "{ line: 2, character: 0 }");
+            assert_eq!(&src[*end], "{ line: 1, character: 4 }");
+        }
+        e => panic!("expected an invalid range, got {:?}", e),
+    }
+    match &errors[1] {
+        Error::UnknownLabel { range, label } => {
+            assert_eq!(label, "range");
+            assert_eq!(&src[*range], "\"range\"");
+        }
+        e => panic!("expected an unknown label, got {:?}", e),
+    }
+    match &errors[2] {
+        Error::MissingProperty {
+            label, property, ..
+        } => {
+            assert_eq!(label.as_deref(), Some("resultSet"));
+            assert_eq!(property, "id");
+        }
+        e => panic!("expected a missing property, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_duplicated_ids() {
+    let src = "{ id: 1, type: \"vertex\", label: \"resultSet\" }\n\
+               { id: \"1\", type: \"vertex\", label: \"resultSet\" }\n\
+               { id: 1, type: \"edge\", label: \"next\", outV: 1, inV: 1 }";
+    let ids = |root: &RootNode| {
+        root.errors()
+            .iter()
+            .filter_map(|e| match e {
+                Error::DuplicatedId { first, second, .. } => Some((&src[*first], *second)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let root = parser::parse(src).into_dom();
+    let errors = ids(&root);
+    assert_eq!(errors.len(), 1, "{:?}", root.errors());
+    assert_eq!(errors[0].0, "1");
+    assert_eq!(&src[errors[0].1], "1");
+    assert!(errors[0].1.start() > TextSize::from(90));
+
+    let options = Options {
+        distinct_id_types: false,
+        ..Options::default()
+    };
+    let root = parser::parse(src).into_dom_with(&options);
+    let errors = ids(&root);
+    assert_eq!(errors.len(), 2, "{:?}", root.errors());
+    assert_eq!(&src[errors[0].1], "\"1\"");
+
+    // separators are ignored, and integers above i64::MAX
+    // don't collide with strings of the same text
+    let src = "{ id: 1_0, type: \"vertex\", label: \"resultSet\" }\n\
+               { id: 10, type: \"vertex\", label: \"resultSet\" }\n\
+               { id: 99999999999999999999, type: \"vertex\", label: \"resultSet\" }\n\
+               { id: \"99999999999999999999\", type: \"vertex\", label: \"resultSet\" }\n\
+               { id: 0_99999999999999999999, type: \"vertex\", label: \"resultSet\" }";
+    let ids = |root: &RootNode| {
+        root.errors()
+            .iter()
+            .filter_map(|e| match e {
+                Error::DuplicatedId { id, .. } => Some(id.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let root = parser::parse(src).into_dom();
+    assert_eq!(ids(&root), vec!["10", "0_99999999999999999999"]);
+
+    let root = parser::parse(src).into_dom_with(&options);
+    assert_eq!(
+        ids(&root),
+        vec!["10", "\"99999999999999999999\"", "0_99999999999999999999"]
+    );
+}
+
+#[test]
+fn test_dangling_edges() {
+    let src = "{ id: 1, type: \"vertex\", label: \"document\" }\n\
+               { id: 2, type: \"edge\", label: \"contains\", outV: 1, inVs: [3, 4] }\n\
+               { id: 3, type: \"vertex\", label: \"range\" }\n\
+               { id: 5, type: \"edge\", label: \"next\", outV: 3, inV: 2 }";
+    let dangling = |root: &RootNode| {
+        root.errors()
+            .iter()
+            .filter_map(|e| match e {
+                Error::DanglingEdge {
+                    edge, missing_id, ..
+                } => Some((src[*edge].to_string(), missing_id.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let contains = "{ id: 2, type: \"edge\", label: \"contains\", outV: 1, inVs: [3, 4] }";
+    let next = "{ id: 5, type: \"edge\", label: \"next\", outV: 3, inV: 2 }";
+
+    let root = parser::parse(src).into_dom();
+    assert_eq!(
+        dangling(&root),
+        vec![
+            (contains.to_string(), "4".to_string()),
+            (next.to_string(), "2".to_string())
+        ]
+    );
+
+    let options = Options {
+        streaming: true,
+        ..Options::default()
+    };
+    let root = parser::parse(src).into_dom_with(&options);
+    assert_eq!(
+        dangling(&root),
+        vec![
+            (contains.to_string(), "3".to_string()),
+            (contains.to_string(), "4".to_string()),
+            (next.to_string(), "2".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_endpoints() {
+    let src = "{ id: 1, type: \"vertex\", label: \"document\" }\n\
+               { id: 2, type: \"vertex\", label: \"range\" }\n\
+               { id: 3, type: \"vertex\", label: \"resultSet\" }\n\
+               { id: 4, type: \"vertex\", label: \"definitionResult\" }\n\
+               { id: 5, type: \"edge\", label: \"contains\", outV: 1, inVs: [2, 3] }\n\
+               { id: 6, type: \"edge\", label: \"next\", outV: 2, inV: 3 }\n\
+               { id: 7, type: \"edge\", label: \"textDocument/definition\", outV: 3, inV: 4 }\n\
+               { id: 8, type: \"edge\", label: \"item\", outV: 4, inVs: [2], document: 1 }\n\
+               { id: 9, type: \"edge\", label: \"next\", outV: 4, inV: 3 }";
+    let root = parser::parse(src).into_dom();
+    let errors = root
+        .errors()
+        .iter()
+        .filter_map(|e| match e {
+            Error::WrongEndpointType {
+                endpoint,
+                edge_label,
+                vertex_label,
+                ..
+            } => Some((&src[*endpoint], edge_label.as_str(), vertex_label.as_str())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            ("3", "contains", "resultSet"),
+            ("4", "next", "definitionResult")
+        ]
+    );
+}
+
+#[test]
+fn test_schema() {
+    let src = "{ id: 1, type: \"vertex\", label: \"document\", uri: 1, languageId: \"rust\", x: true }\n\
+               { id: 2, type: \"vertex\", label: \"range\", start: { line: 0, character: \"1\" } }\n\
+               { id: 3, type: \"edge\", label: \"item\", outV: 2, inVs: [2] }";
+    let errors = |src: &str, options: &Options| {
+        parser::parse(src)
+            .into_dom_with(options)
+            .errors()
+            .iter()
+            .filter_map(|e| {
+                let range = &src[e.text_range().unwrap()];
+                match e {
+                    Error::MissingProperty { property, .. } => {
+                        Some(format!("missing {} ({})", property, range))
+                    }
+                    Error::MistypedProperty { property, .. } => {
+                        Some(format!("mistyped {} ({})", property, range))
+                    }
+                    Error::UnknownProperty { property, .. } => {
+                        Some(format!("unknown {} ({})", property, range))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let range =
+        "{ id: 2, type: \"vertex\", label: \"range\", start: { line: 0, character: \"1\" } }";
+    let item = "{ id: 3, type: \"edge\", label: \"item\", outV: 2, inVs: [2] }";
+    assert_eq!(
+        errors(src, &Options::default()),
+        vec![
+            "mistyped uri (1)".to_string(),
+            "unknown x (x)".to_string(),
+            "mistyped character (\"1\")".to_string(),
+            format!("missing end ({})", range),
+            format!("missing shard ({})", item),
+        ]
+    );
+
+    let options = Options {
+        allow_unknown_properties: true,
+        ..Options::default()
+    };
+    assert_eq!(errors(src, &options).len(), 4);
+
+    // 0.4 items may have no document, the required properties
+    // of current dumps are checked against the model
+    let v0_4 = "{ id: 1, type: \"vertex\", label: \"metaData\", version: \"0.4.3\" }\n\
+                { id: 2, type: \"edge\", label: \"item\", outV: 3, inVs: [4] }";
+    assert_eq!(errors(v0_4, &Options::default()), Vec::<String>::new());
+    let current = v0_4.replace("0.4.3", "0.6.0");
+    let moniker =
+        "{ id: 5, type: \"vertex\", label: \"moniker\", scheme: \"tsc\", identifier: \"a\" }";
+    assert_eq!(
+        errors(&format!("{}\n{}", current, moniker), &Options::default()),
+        vec![
+            format!(
+                "missing positionEncoding ({})",
+                current.lines().next().unwrap()
+            ),
+            format!("missing shard ({})", current.lines().nth(1).unwrap().trim()),
+            format!("missing unique ({})", moniker),
+        ]
+    );
+}
+
+#[test]
+fn test_events() {
+    let lines = [
+        r#"{ id: 1, type: "vertex", label: "project", kind: "rust" }"#,
+        r#"{ id: 2, type: "vertex", label: "document", uri: "file:///a.rs", languageId: "rust" }"#,
+        r#"{ id: 3, type: "vertex", label: "document", uri: "file:///b.rs", languageId: "rust" }"#,
+        r#"{ id: 4, type: "vertex", label: "$event", kind: "begin", scope: "project", data: 1 }"#,
+        r#"{ id: 5, type: "vertex", label: "$event", kind: "begin", scope: "document", data: 2 }"#,
+        r#"{ id: 6, type: "vertex", label: "range", start: { line: 0, character: 0 }, end: { line: 0, character: 1 } }"#,
+        r#"{ id: 7, type: "edge", label: "contains", outV: 2, inVs: [6] }"#,
+        r#"{ id: 8, type: "vertex", label: "$event", kind: "end", scope: "document", data: 2 }"#,
+        r#"{ id: 9, type: "vertex", label: "range", start: { line: 1, character: 0 }, end: { line: 1, character: 1 } }"#,
+        r#"{ id: 10, type: "edge", label: "contains", outV: 2, inVs: [9] }"#,
+        r#"{ id: 11, type: "vertex", label: "$event", kind: "begin", scope: "document", data: 3 }"#,
+        r#"{ id: 12, type: "vertex", label: "$event", kind: "end", scope: "project", data: 1 }"#,
+        r#"{ id: 13, type: "vertex", label: "$event", kind: "end", scope: "document", data: 1 }"#,
+    ];
+    let src = lines.join("\n");
+    let root = parser::parse(&src).into_dom();
+    let line = |range: TextRange| lines.iter().position(|l| *l == &src[range]).unwrap() + 1;
+
+    let errors = root
+        .errors()
+        .iter()
+        .filter_map(|e| match e {
+            Error::UnbalancedEvent { range, begin } => {
+                Some(format!("unbalanced {} {:?}", line(*range), begin.map(line)))
+            }
+            Error::UnendedEvent { begin } => Some(format!("unended {}", line(*begin))),
+            Error::ElementAfterEnd { element, end } => {
+                Some(format!("after {} {}", line(*element), line(*end)))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "unbalanced 12 Some(11)",
+            "unbalanced 13 None",
+            "unended 11",
+            "after 10 8",
+            "after 9 8",
+        ]
+    );
+}
+
+#[test]
+fn test_value() {
+    let src = "{ id: 1, type: \"vertex\", label: \"hoverResult\", result: { contents: [\"a\\tb\", null], big: 18446744073709551615, neg: -3, ok: true } }";
+    let root = parser::parse(src).into_dom();
+    let value = Value::try_from(root).unwrap();
+    let element = value.as_array().unwrap()[0].as_map().unwrap();
+    assert_eq!(
+        element.keys().collect::<Vec<_>>(),
+        vec!["id", "type", "label", "result"]
+    );
+    assert_eq!(element["id"], Value::Integer(1));
+
+    let result = element["result"].as_map().unwrap();
+    assert_eq!(
+        result["contents"],
+        Value::Array(vec![Value::String("a\tb".into()), Value::Null])
+    );
+    assert_eq!(result["big"], Value::UnsignedInteger(u64::MAX));
+    assert_eq!(result["neg"].as_i64(), Some(-3));
+    assert_eq!(result["ok"].as_bool(), Some(true));
+
+    let src = "{ id: 1, type: \"vertex\", label: \"range\", x: 18446744073709551616 }";
+    let root = parser::parse(src).into_dom();
+    match Value::try_from(root) {
+        Err(e) => assert_eq!(&src[e.text_range().unwrap()], "18446744073709551616"),
+        Ok(v) => panic!("expected an overflow, got {:?}", v),
+    }
+}
+
+#[test]
+fn test_entries() {
+    let lines = [
+        r#"{ id: 1, type: "vertex", label: "metaData", version: "0.6.0", positionEncoding: "utf-16" }"#,
+        r#"{ id: 2, type: "vertex", label: "document", uri: "file:///a.rs", languageId: "rust" }"#,
+        r#"{ id: 3, type: "vertex", label: "$event", kind: "begin", scope: "document", data: 2 }"#,
+        r#"{ id: 4, type: "vertex", label: "range", start: { line: 0, character: 3 }, end: { line: 0, character: 7 } }"#,
+        r#"{ id: 5, type: "edge", label: "contains", outV: 2, inVs: [4] }"#,
+        r#"{ id: 6, type: "vertex", label: "$event", kind: "end", scope: "document", data: 2 }"#,
+    ];
+    let (entries, errors) = entry::from_parse(parser::parse(&lines.join("\n")));
+    assert_eq!(errors, vec![]);
+    assert_eq!(entries.len(), 6);
+    assert_eq!(entries[4].id, Id::from(5));
+    match &entries[3].data {
+        lsif_type::Element::Vertex(lsif_type::Vertex::Range { range, .. }) => {
+            assert_eq!((range.start.character, range.end.character), (3, 7))
+        }
+        e => panic!("expected a range, got {:?}", e),
+    }
+
+    // shape errors are at the rejected values, and not
+    // reported again for elements with errors of the DOM
+    let lines = [
+        r#"{ id: 1, type: "vertex", label: "$event", kind: "middle", scope: "document", data: 2 }"#,
+        r#"{ id: 2, type: "vertex", label: "range", start: { line: -1, character: 3 }, end: { line: 0, character: 7 } }"#,
+        r#"{ id: 3, type: "vertex", label: "document", languageId: "rust" }"#,
+        r#"{ id: 4, type: "vertex", label: "document", uri: "file:///b.rs", languageId: "rust" }"#,
+        r#"{ id: 5, type: "vertex", label: "moniker", scheme: "tsc", identifier: "a", unique: "scheme", kind: "scheme" }"#,
+    ];
+    let src = lines.join("\n");
+    let (entries, errors) = entry::from_parse(parser::parse(&src));
+    assert_eq!(entries.len(), 1);
+    let errors = errors
+        .iter()
+        .map(|e| match e {
+            Error::Spanned { range, message } => format!("{} {}", &src[*range], message),
+            Error::MissingProperty { property, .. } => format!("missing {}", property),
+            e => panic!("unexpected error {:?}", e),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "missing uri",
+            r#""middle" invalid "kind": unknown variant `middle`, expected one of begin, end"#,
+            r#"-1 invalid "start.line": invalid value: integer `-1`, expected u64"#,
+            r#""scheme" invalid "kind": unknown variant `scheme`, expected one of import, export, local"#,
+        ]
+    );
+}
@@ -1,299 +1,271 @@
-// //! This module is used to convert the DOM
-// //! nodes into the values they contain.
-//
-// use crate::{util::unescape, dom};
-// // use crate::{dom, util::unescape};
-// use indexmap::IndexMap;
-// use std::convert::{TryFrom, TryInto};
-//
-// /// This occurs when a key has an invalid escape
-// /// sequence.
-// #[derive(Debug)]
-// pub struct UnescapeError;
-//
-// impl core::fmt::Display for UnescapeError {
-//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-//         write!(f, "the key contains invalid escape sequence")
-//     }
-// }
-//
-// impl std::error::Error for UnescapeError {}
-//
-// pub type Map = IndexMap<String, Value>;
-//
-// /// Contains all possible value types in a TOML document.
-// #[derive(Debug, Clone, PartialEq)]
-// pub enum Value {
-//     Bool(bool),
-//     UnsizedInteger(u64),
-//     Integer(i64),
-//     String(String),
-//     Array(Vec<Value>),
-//     Map(Map),
-// }
-//
-// impl Value {
-//     pub fn as_bool(&self) -> Option<&bool> {
-//         match self {
-//             Value::Bool(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn into_bool(self) -> Option<bool> {
-//         match self {
-//             Value::Bool(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn as_u64(&self) -> Option<&u64> {
-//         match self {
-//             Value::UnsizedInteger(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn into_u64(self) -> Option<u64> {
-//         match self {
-//             Value::UnsizedInteger(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn as_i64(&self) -> Option<&i64> {
-//         match self {
-//             Value::Integer(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn into_i64(self) -> Option<i64> {
-//         match self {
-//             Value::Integer(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn as_f64(&self) -> Option<&f64> {
-//         match self {
-//             Value::Float(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn into_f64(self) -> Option<f64> {
-//         match self {
-//             Value::Float(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn as_str(&self) -> Option<&str> {
-//         match self {
-//             Value::String(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn into_string(self) -> Option<String> {
-//         match self {
-//             Value::String(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn as_array(&self) -> Option<&Vec<Value>> {
-//         match self {
-//             Value::Array(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn into_array(self) -> Option<Vec<Value>> {
-//         match self {
-//             Value::Array(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn as_map(&self) -> Option<&Map> {
-//         match self {
-//             Value::Map(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-//
-//     pub fn into_map(self) -> Option<Map> {
-//         match self {
-//             Value::Map(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-// }
-//
-// impl TryFrom<dom::Node> for Value {
-//     type Error = Error;
-//
-//     fn try_from(node: dom::Node) -> Result<Self, Self::Error> {
-//         match node {
-//             dom::Node::Root(v) => v.try_into(),
-//             dom::Node::Table(v) => v.try_into(),
-//             dom::Node::Value(v) => v.try_into(),
-//             dom::Node::Array(v) => v.try_into(),
-//             _ => Err(dom::Error::Spanned {
-//                 range: node.text_range(),
-//                 message: format!(
-//                     "cannot convert {:?} directly to value without context",
-//                     node.kind()
-//                 ),
-//             }
-//                 .into()),
-//         }
-//     }
-// }
-//
-// impl TryFrom<dom::RootNode> for Value {
-//     type Error = Error;
-//     fn try_from(node: dom::RootNode) -> Result<Self, Self::Error> {
-//         Ok(Value::Map(
-//             node.into_entries()
-//                 .into_iter()
-//                 .try_fold::<_, _, Result<IndexMap<String, Value>, Self::Error>>(
-//                     IndexMap::new(),
-//                     |mut m, entry| {
-//                         m.insert(
-//                             unescape(&entry.key().full_key_string_stripped())
-//                                 .map_err(|_| UnescapeError)?,
-//                             entry.into_value().try_into()?,
-//                         );
-//                         Ok(m)
-//                     },
-//                 )?,
-//         ))
-//     }
-// }
-//
-// impl TryFrom<dom::TableNode> for Value {
-//     type Error = Error;
-//     fn try_from(node: dom::TableNode) -> Result<Self, Self::Error> {
-//         Ok(Value::Map(
-//             node.into_entries()
-//                 .into_iter()
-//                 .try_fold::<_, _, Result<IndexMap<String, Value>, Self::Error>>(
-//                     IndexMap::new(),
-//                     |mut m, entry| {
-//                         m.insert(
-//                             unescape(&entry.key().full_key_string_stripped())
-//                                 .map_err(|_| UnescapeError)?,
-//                             entry.into_value().try_into()?,
-//                         );
-//                         Ok(m)
-//                     },
-//                 )?,
-//         ))
-//     }
-// }
-//
-// impl TryFrom<dom::ArrayNode> for Value {
-//     type Error = Error;
-//     fn try_from(node: dom::ArrayNode) -> Result<Self, Self::Error> {
-//         Ok(Value::Array(
-//             node.into_items()
-//                 .into_iter()
-//                 .map(Value::try_from)
-//                 .collect::<Result<Vec<Value>, Self::Error>>()?,
-//         ))
-//     }
-// }
-//
-// impl TryFrom<dom::ValueNode> for Value {
-//     type Error = Error;
-//     fn try_from(node: dom::ValueNode) -> Result<Self, Self::Error> {
-//         Ok(match node {
-//             dom::ValueNode::Bool(v) => v.try_into()?,
-//             dom::ValueNode::String(v) => v.try_into()?,
-//             dom::ValueNode::Integer(v) => v.try_into()?,
-//             dom::ValueNode::Array(v) => v.try_into()?,
-//             dom::ValueNode::Table(v) => v.try_into()?,
-//             dom::ValueNode::Invalid(_) => unimplemented!(),
-//             _ => panic!("empty node"),
-//         })
-//     }
-// }
-//
-// impl TryFrom<dom::BoolNode> for Value {
-//     type Error = Error;
-//     fn try_from(node: dom::BoolNode) -> Result<Self, Self::Error> {
-//         Ok(Value::Bool(node.to_string().parse()?))
-//     }
-// }
-//
-// impl TryFrom<dom::StringNode> for Value {
-//     type Error = Error;
-//     fn try_from(node: dom::StringNode) -> Result<Self, Self::Error> {
-//         Ok(match node.string_kind() {
-//             dom::StringKind::Basic => Value::String(node.into_content()),
-//             dom::StringKind::MultiLine => Value::String(node.into_content()),
-//             dom::StringKind::Literal => Value::String(node.into_content()),
-//             dom::StringKind::MultiLineLiteral => Value::String(node.into_content()),
-//         })
-//     }
-// }
-//
-// impl TryFrom<dom::IntegerNode> for Value {
-//     type Error = Error;
-//     fn try_from(node: dom::IntegerNode) -> Result<Self, Self::Error> {
-//         let node_str = node.to_string().replace("_", "");
-//
-//         Ok(match node.repr() {
-//             dom::IntegerRepr::Dec => match i64::from_str_radix(&node_str, 10) {
-//                 Ok(i) => Value::Integer(i),
-//                 Err(_) => Value::UnsizedInteger(u64::from_str_radix(&node_str, 10)?),
-//             },
-//
-//             dom::IntegerRepr::Bin => {
-//                 match i64::from_str_radix(&node_str.trim_start_matches("0b"), 2) {
-//                     Ok(i) => Value::Integer(i),
-//                     Err(_) => Value::UnsizedInteger(u64::from_str_radix(
-//                         &node_str.trim_start_matches("0b"),
-//                         2,
-//                     )?),
-//                 }
-//             }
-//             dom::IntegerRepr::Oct => {
-//                 match i64::from_str_radix(&node_str.trim_start_matches("0o"), 8) {
-//                     Ok(i) => Value::Integer(i),
-//                     Err(_) => Value::UnsizedInteger(u64::from_str_radix(
-//                         &node_str.trim_start_matches("0o"),
-//                         8,
-//                     )?),
-//                 }
-//             }
-//             dom::IntegerRepr::Hex => {
-//                 match i64::from_str_radix(&node_str.trim_start_matches("0x"), 16) {
-//                     Ok(i) => Value::Integer(i),
-//                     Err(_) => Value::UnsizedInteger(u64::from_str_radix(
-//                         &node_str.trim_start_matches("0x"),
-//                         16,
-//                     )?),
-//                 }
-//             }
-//         })
-//     }
-// }
-//
-// #[derive(Debug)]
-// pub struct Error(Box<dyn std::error::Error>);
-//
-// impl core::fmt::Display for Error {
-//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-//         self.0.fmt(f)
-//     }
-// }
-//
-// impl<E: std::error::Error + 'static> From<E> for Error {
-//     fn from(e: E) -> Self {
-//         Self(Box::new(e))
-//     }
-// }
+//! This module is used to convert the DOM
+//! nodes into the values they contain.
+
+use crate::dom::{self, Common};
+use indexmap::IndexMap;
+use lsif_parser_lib::util::unescape;
+use rowan::TextRange;
+use std::convert::{TryFrom, TryInto};
+
+pub type Map = IndexMap<String, Value>;
+
+/// The errors of the conversions are spanned DOM errors.
+pub type Error = dom::Error;
+
+/// Contains all possible value types in an LSIF dump.
+///
+/// The parser has no float literals, so the values
+/// converted from the DOM are never floats.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    /// Integers above `i64::MAX`.
+    UnsignedInteger(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Properties, in the order of the dump.
+    Map(Map),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Integer(v) => u64::try_from(*v).ok(),
+            Value::UnsignedInteger(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn into_string(self) -> Option<String> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn into_array(self) -> Option<Vec<Value>> {
+        match self {
+            Value::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match self {
+            Value::Map(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn into_map(self) -> Option<Map> {
+        match self {
+            Value::Map(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<dom::Node> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::Node) -> Result<Self, Self::Error> {
+        match node {
+            dom::Node::Root(v) => v.try_into(),
+            dom::Node::Vertex(v) => v.try_into(),
+            dom::Node::Edge(v) => v.try_into(),
+            dom::Node::Value(v) => v.try_into(),
+            _ => Err(Error::Spanned {
+                range: node.text_range(),
+                message: format!(
+                    "cannot convert {:?} directly to value without context",
+                    node.kind()
+                ),
+            }),
+        }
+    }
+}
+
+/// The elements of the dump, in order.
+impl TryFrom<dom::RootNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::RootNode) -> Result<Self, Self::Error> {
+        Ok(Value::Array(
+            node.into_elements()
+                .into_iter()
+                .map(Value::try_from)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+impl TryFrom<dom::ElementNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::ElementNode) -> Result<Self, Self::Error> {
+        match node {
+            dom::ElementNode::Vertex(v) => v.try_into(),
+            dom::ElementNode::Edge(v) => v.try_into(),
+        }
+    }
+}
+
+impl TryFrom<dom::VertexNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::VertexNode) -> Result<Self, Self::Error> {
+        entries_to_map(node.into_entries())
+    }
+}
+
+impl TryFrom<dom::EdgeNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::EdgeNode) -> Result<Self, Self::Error> {
+        entries_to_map(node.into_entries())
+    }
+}
+
+impl TryFrom<dom::TableNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::TableNode) -> Result<Self, Self::Error> {
+        entries_to_map(node.into_entries())
+    }
+}
+
+impl TryFrom<dom::ArrayNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::ArrayNode) -> Result<Self, Self::Error> {
+        Ok(Value::Array(
+            node.into_items()
+                .into_iter()
+                .map(Value::try_from)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+impl TryFrom<dom::ValueNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::ValueNode) -> Result<Self, Self::Error> {
+        match node {
+            dom::ValueNode::Bool(v) => v.try_into(),
+            dom::ValueNode::String(v) => v.try_into(),
+            dom::ValueNode::Integer(v) => v.try_into(),
+            dom::ValueNode::Array(v) => v.try_into(),
+            dom::ValueNode::Table(v) => v.try_into(),
+            // `null` is not a keyword of the lexer
+            dom::ValueNode::Invalid(v) if v.to_string() == "null" => Ok(Value::Null),
+            dom::ValueNode::Invalid(v) => Err(Error::Spanned {
+                range: v.text_range(),
+                message: "invalid value".into(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<dom::BoolNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::BoolNode) -> Result<Self, Self::Error> {
+        Ok(Value::Bool(node.to_string() == "true"))
+    }
+}
+
+impl TryFrom<dom::StringNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::StringNode) -> Result<Self, Self::Error> {
+        unescape_spanned(&node.to_string(), node.text_range()).map(Value::String)
+    }
+}
+
+impl TryFrom<dom::IntegerNode> for Value {
+    type Error = Error;
+
+    fn try_from(node: dom::IntegerNode) -> Result<Self, Self::Error> {
+        if let Some(i) = node.value() {
+            return Ok(Value::Integer(i));
+        }
+
+        let digits = node.digits();
+        match digits.parse::<u64>() {
+            Ok(u) => Ok(Value::UnsignedInteger(u)),
+            Err(_) => Err(Error::Spanned {
+                range: node.text_range(),
+                message: format!("integer {} is out of range", digits),
+            }),
+        }
+    }
+}
+
+fn entries_to_map(entries: dom::Entries) -> Result<Value, Error> {
+    let mut map = Map::with_capacity(entries.len());
+    for entry in entries {
+        let key = entry.key();
+        let text = key
+            .token()
+            .map(|t| t.text().to_string())
+            .unwrap_or_default();
+        let key = unescape_spanned(&text, key.text_range())?;
+        map.insert(key, entry.into_value().try_into()?);
+    }
+    Ok(Value::Map(map))
+}
+
+/// Strip the quotes, and resolve the escapes of a string or a key.
+fn unescape_spanned(s: &str, range: TextRange) -> Result<String, Error> {
+    let stripped = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s);
+    unescape(stripped).map_err(|i| Error::Spanned {
+        range,
+        message: format!("invalid escape sequence at offset {}", i),
+    })
+}
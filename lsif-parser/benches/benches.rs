@@ -2,33 +2,314 @@
 
 extern crate test;
 
-use std::fs;
+use lsif_parser_lib::parser::{self, NodeCache, ParseOptions};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use test::Bencher;
 
+/// The system allocator, counting what is allocated
+/// to compare the memory used by the parse options.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE.fetch_add(new_size, Ordering::Relaxed);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// The bytes and the number of allocations made by `f`, and the bytes
+/// still allocated while its result is alive.
+fn measure<T>(f: impl FnOnce() -> T) -> (usize, usize, usize) {
+    let (allocated, allocations, live) = (
+        ALLOCATED.load(Ordering::Relaxed),
+        ALLOCATIONS.load(Ordering::Relaxed),
+        LIVE.load(Ordering::Relaxed),
+    );
+    let result = f();
+    let measured = (
+        ALLOCATED.load(Ordering::Relaxed) - allocated,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        LIVE.load(Ordering::Relaxed).saturating_sub(live),
+    );
+    drop(result);
+    measured
+}
+
+/// The number of ranges of the synthetic dumps, about 4 MB.
+const DUMP_RANGES: usize = 5_000;
+
+/// How the lines of a synthetic dump are formatted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    /// Minified JSON, as the indexers write it.
+    Minified,
+    /// A space after every `,` and `:`.
+    Spaced,
+    /// Spaced, and a comment after every line.
+    Commented,
+}
+
+/// A dump in the shape of the output of the indexers, as
+/// `samples/` is not always there: a document with ranges that
+/// have a result set, a definition and a hover each.
+fn synthetic(ranges: usize, style: Style) -> String {
+    let mut lines = vec![
+        r#"{"id":1,"type":"vertex","label":"metaData","version":"0.6.0","positionEncoding":"utf-16"}"#.to_string(),
+        r#"{"id":2,"type":"vertex","label":"document","uri":"file:///sample/src/main.ts","languageId":"typescript"}"#.to_string(),
+    ];
+    let mut id = 2;
+    let mut range_ids = Vec::with_capacity(ranges);
+    for i in 0..ranges {
+        let (line, character) = (i / 10, i % 10 * 8);
+        let range = id + 1;
+        range_ids.push(range.to_string());
+        lines.extend(vec![
+            format!(r#"{{"id":{},"type":"vertex","label":"range","start":{{"line":{},"character":{}}},"end":{{"line":{},"character":{}}},"tag":{{"type":"reference","text":"name{}"}}}}"#, range, line, character, line, character + 6, i),
+            format!(r#"{{"id":{},"type":"vertex","label":"resultSet"}}"#, range + 1),
+            format!(r#"{{"id":{},"type":"edge","label":"next","outV":{},"inV":{}}}"#, range + 2, range, range + 1),
+            format!(r#"{{"id":{},"type":"vertex","label":"definitionResult"}}"#, range + 3),
+            format!(r#"{{"id":{},"type":"edge","label":"textDocument/definition","outV":{},"inV":{}}}"#, range + 4, range + 1, range + 3),
+            format!(r#"{{"id":{},"type":"edge","label":"item","outV":{},"inVs":[{}],"shard":2}}"#, range + 5, range + 3, range),
+            format!(r#"{{"id":{},"type":"vertex","label":"hoverResult","result":{{"contents":{{"kind":"markdown","value":"```ts\nconst name{}: number\n```"}}}}}}"#, range + 6, i),
+            format!(r#"{{"id":{},"type":"edge","label":"textDocument/hover","outV":{},"inV":{}}}"#, range + 7, range + 1, range + 6),
+        ]);
+        id += 8;
+    }
+    lines.push(format!(
+        r#"{{"id":{},"type":"edge","label":"contains","outV":2,"inVs":[{}]}}"#,
+        id + 1,
+        range_ids.join(",")
+    ));
+    if style != Style::Minified {
+        lines = lines.iter().map(|l| space(l)).collect();
+    }
+    if style == Style::Commented {
+        for (i, line) in lines.iter_mut().enumerate() {
+            line.push_str(&format!(" // line {}", i + 1));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Add a space after the `,` and `:` of the line that are not in strings.
+fn space(line: &str) -> String {
+    let mut spaced = String::with_capacity(line.len() * 5 / 4);
+    let (mut in_string, mut escaped) = (false, false);
+    for c in line.chars() {
+        spaced.push(c);
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' | ':' if !in_string => spaced.push(' '),
+            _ => {}
+        }
+    }
+    spaced
+}
+
 #[bench]
 fn test_bench_real(b: &mut Bencher) {
     let str = fs::read_to_string("../samples/jsonRPC.lsif").unwrap();
     b.iter(|| {
-        lsif_parser_lib::parser::parse(&str);
+        parser::parse(&str);
+    });
+}
+
+#[bench]
+fn test_bench_real_cached(b: &mut Bencher) {
+    let str = fs::read_to_string("../samples/jsonRPC.lsif").unwrap();
+    let mut cache = NodeCache::default();
+    b.iter(|| {
+        ParseOptions::new().cache(&mut cache).parse(&str);
+    });
+}
+
+#[bench]
+fn test_bench_real_compact(b: &mut Bencher) {
+    let str = fs::read_to_string("../samples/jsonRPC.lsif").unwrap();
+    b.iter(|| {
+        ParseOptions::new().compact().parse(&str);
+    });
+}
+
+#[bench]
+fn test_bench_scan(b: &mut Bencher) {
+    let str = fs::read_to_string("../samples/jsonRPC.lsif").unwrap();
+    b.iter(|| lsif_parser_lib::util::scan::lines(&str).count());
+}
+
+#[bench]
+fn test_bench_real_filtered(b: &mut Bencher) {
+    let str = fs::read_to_string("../samples/jsonRPC.lsif").unwrap();
+    b.iter(|| {
+        ParseOptions::new()
+            .filter(&mut |line| line.type_(&str) == Some("edge"))
+            .parse(&str);
+    });
+}
+
+#[bench]
+fn test_bench_synthetic(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Spaced);
+    b.iter(|| parser::parse(&str));
+}
+
+#[bench]
+fn test_bench_synthetic_compact(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Spaced);
+    b.iter(|| ParseOptions::new().compact().parse(&str));
+}
+
+#[bench]
+fn test_bench_synthetic_commented(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Commented);
+    b.iter(|| parser::parse(&str));
+}
+
+#[bench]
+fn test_bench_synthetic_commented_compact(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Commented);
+    b.iter(|| ParseOptions::new().compact().parse(&str));
+}
+
+#[bench]
+fn test_bench_synthetic_minified(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Minified);
+    b.iter(|| parser::parse(&str));
+}
+
+#[bench]
+fn test_bench_synthetic_minified_compact(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Minified);
+    b.iter(|| ParseOptions::new().compact().parse(&str));
+}
+
+#[bench]
+fn test_bench_synthetic_serde(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Spaced);
+    b.iter(|| serde_lines(&str));
+}
+
+#[bench]
+fn test_bench_synthetic_filtered_edges(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Spaced);
+    b.iter(|| {
+        ParseOptions::new()
+            .filter(&mut |line| line.type_(&str) == Some("edge"))
+            .parse(&str)
     });
 }
 
+#[bench]
+fn test_bench_synthetic_filtered_next(b: &mut Bencher) {
+    let str = synthetic(DUMP_RANGES, Style::Spaced);
+    b.iter(|| {
+        ParseOptions::new()
+            .filter(&mut |line| line.label(&str) == Some("next"))
+            .parse(&str)
+    });
+}
+
+/// Every line of the dump read by serde_json into a `Value`,
+/// which is what the parse options are compared to.
+fn serde_lines(str: &str) -> Vec<serde_json::Value> {
+    str.lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+/// Prints the memory used by the parse options on the synthetic dumps,
+/// with `cargo bench -- --nocapture memory`.
+#[bench]
+fn test_bench_memory(b: &mut Bencher) {
+    fn row(name: &str, (allocated, allocations, live): (usize, usize, usize)) {
+        println!(
+            "{:<28} {:>9.1} MB allocated in {:>9} allocations, {:>6.1} MB kept",
+            name,
+            allocated as f64 / 1e6,
+            allocations,
+            live as f64 / 1e6
+        );
+    }
+
+    for style in &[Style::Minified, Style::Spaced, Style::Commented] {
+        let str = synthetic(DUMP_RANGES, *style);
+        println!("{:?} dump of {:.1} MB", style, str.len() as f64 / 1e6);
+        row("lossless", measure(|| parser::parse(&str)));
+        row(
+            "compact",
+            measure(|| ParseOptions::new().compact().parse(&str)),
+        );
+        let mut cache = NodeCache::default();
+        ParseOptions::new().cache(&mut cache).parse(&str);
+        row(
+            "lossless, warm cache",
+            measure(|| ParseOptions::new().cache(&mut cache).parse(&str)),
+        );
+        row(
+            "edges only",
+            measure(|| {
+                ParseOptions::new()
+                    .filter(&mut |line| line.type_(&str) == Some("edge"))
+                    .parse(&str)
+            }),
+        );
+        row(
+            "next edges only",
+            measure(|| {
+                ParseOptions::new()
+                    .filter(&mut |line| line.label(&str) == Some("next"))
+                    .parse(&str)
+            }),
+        );
+        // serde_json doesn't read comments.
+        if *style == Style::Commented {
+            continue;
+        }
+        row("serde_json Value per line", measure(|| serde_lines(&str)));
+    }
+    b.iter(|| ());
+}
+
 #[bench]
 fn test_bench(b: &mut Bencher) {
     let str = "{ \"id\": 5, \"type\": \"edge\", \"label\": \"contains\", \"outV\": 1, \"inVs\": [4] }";
     // let str = "{ id: 5, type: \"edge\", label: \"contains\", outV: 1, inVs: [4] }";
 
     b.iter(|| {
-        lsif_parser_lib::parser::parse(&str);
+        parser::parse(str);
     });
 }
 
 #[bench]
 fn test_bench_serde(b: &mut Bencher) {
-    use serde_json::{Result, Value};
-
     let str = fs::read_to_string("../samples/jsonRPC.lsif").unwrap();
-    b.iter(|| {
-        let _: Result<Value> = serde_json::from_str(&str);
-    });
+    b.iter(|| serde_lines(&str));
 }
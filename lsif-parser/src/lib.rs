@@ -1,4 +1,5 @@
 pub mod parser;
+pub mod semantic_tokens;
 pub mod syntax;
 pub mod util;
 
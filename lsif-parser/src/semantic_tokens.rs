@@ -0,0 +1,160 @@
+//! LSP semantic tokens for LSIF files.
+//!
+//! The tokens are classified from the syntax tree, and their positions
+//! are computed with a [`Mapper`], so its encoding must be the one
+//! negotiated with the client.
+
+use crate::{
+    syntax::{SyntaxKind::*, SyntaxNode, SyntaxToken},
+    util::{
+        coords::{relative_position, Mapper, Position, SplitLines},
+        unquote,
+    },
+};
+use lsp_types::{
+    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensLegend,
+};
+
+/// The token types, in the order of the [legend](legend).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum TokenType {
+    /// Property keys.
+    Property,
+    /// Values of `type` and `label`, e.g. `"vertex"`, `"edge"` or `"next"`.
+    Label,
+    /// Element ids and references to them.
+    Id,
+    Number,
+    String,
+    Keyword,
+    Comment,
+    Error,
+}
+
+impl TokenType {
+    pub fn lsp_type(self) -> SemanticTokenType {
+        match self {
+            TokenType::Property => SemanticTokenType::PROPERTY,
+            TokenType::Label => SemanticTokenType::ENUM_MEMBER,
+            TokenType::Id => SemanticTokenType::VARIABLE,
+            TokenType::Number => SemanticTokenType::NUMBER,
+            TokenType::String => SemanticTokenType::STRING,
+            TokenType::Keyword => SemanticTokenType::KEYWORD,
+            TokenType::Comment => SemanticTokenType::COMMENT,
+            TokenType::Error => SemanticTokenType::new("error"),
+        }
+    }
+}
+
+const TOKEN_TYPES: &[TokenType] = &[
+    TokenType::Property,
+    TokenType::Label,
+    TokenType::Id,
+    TokenType::Number,
+    TokenType::String,
+    TokenType::Keyword,
+    TokenType::Comment,
+    TokenType::Error,
+];
+
+/// The token modifiers, as bits of the modifier set.
+pub mod modifier {
+    /// The `id` of an element, as opposed to a reference to it.
+    pub const DECLARATION: u32 = 1 << 0;
+}
+
+/// The legend to register in the server capabilities.
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.iter().map(|t| t.lsp_type()).collect(),
+        token_modifiers: vec![SemanticTokenModifier::DECLARATION],
+    }
+}
+
+/// Keys of the sentences whose values are ids.
+const ID_KEYS: &[&str] = &["id", "outV", "inV", "inVs", "document", "shard"];
+
+/// Keys of the sentences whose values are labels.
+const LABEL_KEYS: &[&str] = &["type", "label"];
+
+/// Classify every token of the tree, and encode them
+/// relative to each other.
+pub fn semantic_tokens(syntax: &SyntaxNode, mapper: &Mapper) -> SemanticTokens {
+    let mut data = Vec::new();
+    let mut last = Position::default();
+
+    for token in syntax.descendants_with_tokens().filter_map(|e| e.into_token()) {
+        let (token_type, modifiers) = match classify(&token) {
+            Some(t) => t,
+            None => continue,
+        };
+        let range = match mapper.range(token.text_range()) {
+            Some(r) => r,
+            None => continue,
+        };
+
+        for range in range.split_lines(mapper) {
+            if range.end.character <= range.start.character {
+                continue;
+            }
+            let relative = relative_position(range.start, last);
+            data.push(SemanticToken {
+                delta_line: relative.line as u32,
+                delta_start: relative.character as u32,
+                length: (range.end.character - range.start.character) as u32,
+                token_type: token_type as u32,
+                token_modifiers_bitset: modifiers,
+            });
+            last = range.start;
+        }
+    }
+
+    SemanticTokens {
+        result_id: None,
+        data,
+    }
+}
+
+fn classify(token: &SyntaxToken) -> Option<(TokenType, u32)> {
+    let token_type = match token.kind() {
+        IDENT if token.parent().map(|p| p.kind()) == Some(KEY) => TokenType::Property,
+        STRING | INTEGER => {
+            let key = sentence_key(token);
+            match key.as_ref().map(|k| unquote(k.text())).as_deref() {
+                Some("id") => return Some((TokenType::Id, modifier::DECLARATION)),
+                Some(key) if ID_KEYS.contains(&key) => TokenType::Id,
+                Some(key) if LABEL_KEYS.contains(&key) => TokenType::Label,
+                _ if token.kind() == INTEGER => TokenType::Number,
+                _ => TokenType::String,
+            }
+        }
+        BOOL => TokenType::Keyword,
+        COMMENT => TokenType::Comment,
+        ERROR => TokenType::Error,
+        _ => return None,
+    };
+    Some((token_type, 0))
+}
+
+/// The key token of the sentence property the value token belongs to,
+/// directly or as an item of an array.
+///
+/// It is the identifier or string token of the KEY node,
+/// as the parser attaches the trivia after a key to the node.
+fn sentence_key(token: &SyntaxToken) -> Option<SyntaxToken> {
+    let mut value = token.parent().filter(|p| p.kind() == VALUE)?;
+    while let Some(list) = value.parent().filter(|p| p.kind() == LIST) {
+        value = list.parent().filter(|p| p.kind() == VALUE)?;
+    }
+    if value.parent()?.kind() != SENTENCE {
+        return None;
+    }
+
+    value
+        .siblings(rowan::Direction::Prev)
+        .find(|n| n.kind() == KEY)?
+        .children_with_tokens()
+        .filter_map(|e| e.into_token())
+        .find(|t| matches!(t.kind(), IDENT | STRING))
+}
@@ -94,7 +94,6 @@ impl rowan::Language for Lang {
 /// It is also immutable, like a GreenNode,
 /// but it contains parent pointers, offsets, and
 /// has identity semantics.
-
 pub type SyntaxNode = rowan::SyntaxNode<Lang>;
 #[allow(unused)]
 pub type SyntaxToken = rowan::SyntaxToken<Lang>;
@@ -116,7 +115,7 @@ fn lex_string(lex: &mut Lexer<SyntaxKind>) -> bool {
         }
 
         if c == '"' && !escaped {
-            lex.bump(remainder[0..total_len].as_bytes().len());
+            lex.bump(total_len);
             return true;
         }
 
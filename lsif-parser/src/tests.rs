@@ -44,4 +44,327 @@ fn test_real() {
     let root = parser::parse(&str);
     let syntax = root.into_syntax();
     assert_eq!(format!("{:?}", syntax), "ROOT@0..5384658");
-}
\ No newline at end of file
+}
+#[test]
+fn test_observer() {
+    use parser::{Control, Progress, ReportInterval};
+
+    let src = "{ id: 1 }\n{ id: 2 }\n{ id: 3 }\n{ id: 4 }";
+    let mut reports = Vec::new();
+    let root = parser::ParseOptions::new()
+        .observer(ReportInterval::Sentences(2), &mut |p: Progress| {
+            reports.push(p.sentences_parsed);
+            Control::Continue
+        })
+        .parse(src);
+    assert!(!root.cancelled);
+    assert!(root.errors.is_empty());
+    // two periodic reports, and the final one.
+    assert_eq!(reports, vec![2, 4, 4]);
+    assert_eq!(format!("{:?}", root.into_syntax()), "ROOT@0..39");
+}
+
+#[test]
+fn test_observer_cancel() {
+    use parser::{Control, Progress, ReportInterval};
+
+    let src = "{ id: 1 }\n{ id: 2 }\n{ id: 3 }\n{ id: 4 }";
+    let root = parser::ParseOptions::new()
+        .observer(ReportInterval::Bytes(12), &mut |p: Progress| {
+            assert!(p.bytes_consumed >= 12);
+            Control::Cancel
+        })
+        .parse(src);
+    assert!(root.cancelled);
+    assert!(root.errors.is_empty());
+    let syntax = root.into_syntax();
+    assert!(u32::from(syntax.text_range().end()) < src.len() as u32);
+    assert_eq!(
+        syntax
+            .children()
+            .next()
+            .map(|s| s.text().to_string()),
+        Some("{ id: 1 }".to_string())
+    );
+}
+
+#[test]
+fn test_shared_cache() {
+    use crate::syntax::SyntaxKind::KEY;
+    use rowan::{GreenNode, GreenNodeData};
+
+    fn first_key(green: &GreenNode) -> *const GreenNodeData {
+        let sentence = green.children().next().unwrap().into_node().unwrap();
+        sentence
+            .children()
+            .filter_map(|c| c.into_node())
+            .find(|n| n.kind() == KEY.into())
+            .unwrap()
+    }
+
+    let src = "{ \"id\": 1, \"type\": \"edge\" }";
+    let mut cache = parser::NodeCache::default();
+    let first = parser::ParseOptions::new().cache(&mut cache).parse(src);
+    let second = parser::ParseOptions::new().cache(&mut cache).parse(src);
+    assert_eq!(first_key(&first.green_node), first_key(&second.green_node));
+
+    let uncached = parser::parse(src);
+    assert_ne!(first_key(&first.green_node), first_key(&uncached.green_node));
+    assert_eq!(first.green_node, uncached.green_node);
+}
+
+#[test]
+fn test_compact() {
+    use crate::syntax::SyntaxKind::{COMMENT, WHITESPACE};
+    use rowan::TextRange;
+
+    fn significant(parse: parser::Parse) -> Vec<String> {
+        let trivia = parse.trivia.clone();
+        parse
+            .into_syntax()
+            .descendants_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| t.kind() != WHITESPACE && t.kind() != COMMENT)
+            .map(|t| format!("{:?}@{:?}", t.kind(), trivia.range(t.text_range())))
+            .collect()
+    }
+
+    let src = "{ id:  1, // comment\n  test: \"value\" }\n{id: 2}  ";
+    let lossless = parser::parse(src);
+    let compact = parser::ParseOptions::new().compact().parse(src);
+    assert!(lossless.trivia.is_empty());
+    assert_eq!(lossless.errors, compact.errors);
+    assert_eq!(
+        compact.clone().into_syntax().to_string(),
+        "{id:1,\ntest:\"value\"}\n{id:2}"
+    );
+    assert_eq!(significant(lossless), significant(compact.clone()));
+
+    // nodes span from their first to their last token.
+    let sentence = compact.clone().into_syntax().last_child().unwrap();
+    assert_eq!(sentence.text_range(), TextRange::new(21.into(), 27.into()));
+    assert_eq!(
+        compact.trivia.range(sentence.text_range()),
+        TextRange::new(39.into(), 46.into())
+    );
+
+    // lines skipped by a filter are left out as well.
+    let filtered = parser::ParseOptions::new()
+        .compact()
+        .filter(&mut |line| line.id(src) == Some("2"))
+        .parse(src);
+    let syntax = filtered.clone().into_syntax();
+    assert_eq!(syntax.to_string(), "\n\n{id:2}");
+    let sentence = syntax.children().next().unwrap();
+    assert_eq!(
+        filtered.trivia.range(sentence.text_range()),
+        TextRange::new(39.into(), 46.into())
+    );
+}
+
+#[test]
+fn test_scan() {
+    use crate::util::scan;
+
+    let src = "{\"id\":1,\"type\":\"vertex\",\"label\":\"range\",\"start\":{\"line\":0}}\r\n\
+               \n\
+               { id: \"2\", data: { label: \"nested\" }, type: \"edge\", label: \"next\" }\n\
+               {\"contents\":\"a \\\" label: \\\"\"}";
+    let lines = scan::lines(src).collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+
+    assert_eq!(&src[lines[0].range], &src[..src.find('\r').unwrap()]);
+    assert_eq!(lines[0].id(src), Some("1"));
+    assert_eq!(lines[0].type_(src), Some("vertex"));
+    assert_eq!(lines[0].label(src), Some("range"));
+
+    assert!(lines[1].range.is_empty());
+    assert_eq!(lines[1].label, None);
+
+    assert_eq!(lines[2].id.map(|r| &src[r]), Some("\"2\""));
+    assert_eq!(lines[2].type_(src), Some("edge"));
+    assert_eq!(lines[2].label(src), Some("next"));
+
+    assert_eq!(lines[3].label, None);
+
+    // a truncated last line
+    let src = "{\"id\":1,\"type\":\"edge\",\"label\":\"nex";
+    let line = scan::lines(src).next().unwrap();
+    assert_eq!(usize::from(line.range.end()), src.len());
+    assert_eq!(line.label.map(|r| usize::from(r.end())), Some(src.len()));
+    assert_eq!(line.label(src), Some("nex"));
+    let src = "{\"type\":\"edge\",\"label";
+    let line = scan::lines(src).next().unwrap();
+    assert_eq!(line.type_(src), Some("edge"));
+    assert_eq!(line.label, None);
+    assert_eq!(line.label(src), None);
+}
+
+#[test]
+fn test_filtered() {
+    use crate::syntax::SyntaxKind::{SENTENCE, WHITESPACE};
+
+    let src = "{ id: 1, type: \"vertex\", label: \"range\" }\n\
+               { id: 2, type: \"edge\", label: \"next\", invalid }\n\
+               { id: 3, type: \"vertex\", label: \"resultSet\" }";
+    let root = parser::ParseOptions::new()
+        .filter(&mut |line| line.type_(src) == Some("vertex"))
+        .parse(src);
+    assert!(root.errors.is_empty());
+
+    let syntax = root.into_syntax();
+    assert_eq!(syntax.text_range(), parser::parse(src).into_syntax().text_range());
+    let sentences = syntax
+        .children()
+        .filter(|n| n.kind() == SENTENCE)
+        .map(|n| n.text().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sentences,
+        vec![
+            "{ id: 1, type: \"vertex\", label: \"range\" }",
+            "{ id: 3, type: \"vertex\", label: \"resultSet\" }"
+        ]
+    );
+    let skipped = syntax
+        .children_with_tokens()
+        .filter(|t| t.kind() == WHITESPACE)
+        .map(|t| u32::from(t.text_range().len()))
+        .collect::<Vec<_>>();
+    assert_eq!(skipped, vec![47]);
+
+    // the filter is asked once per line, even with several sentences on it
+    let src = "{ id: 1 } { id: 2 }\n{ id: 3 }\n{ id: 4 }";
+    let mut asked = Vec::new();
+    let root = parser::ParseOptions::new()
+        .filter(&mut |line| {
+            asked.push(line.id(src).unwrap().to_string());
+            line.id(src) != Some("3")
+        })
+        .parse(src);
+    assert_eq!(asked, vec!["1", "3", "4"]);
+    let sentences = root
+        .into_syntax()
+        .children()
+        .filter(|n| n.kind() == SENTENCE)
+        .map(|n| n.text().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(sentences, vec!["{ id: 1 } ", "{ id: 2 }", "{ id: 4 }"]);
+}
+
+#[test]
+fn test_options() {
+    use crate::syntax::SyntaxKind::SENTENCE;
+    use parser::{Control, Progress, ReportInterval};
+
+    let src = "{ id: 1,  type: \"vertex\" }\n\
+               { id: 2,  type: \"edge\" }\n\
+               { id: 3,  type: \"edge\" }\n\
+               { id: 4,  type: \"edge\" }";
+    let mut cache = parser::NodeCache::default();
+    let root = parser::ParseOptions::new()
+        .compact()
+        .cache(&mut cache)
+        .filter(&mut |line| line.type_(src) == Some("edge"))
+        .observer(ReportInterval::Sentences(2), &mut |p: Progress| {
+            if p.sentences_parsed >= 2 {
+                Control::Cancel
+            } else {
+                Control::Continue
+            }
+        })
+        .parse(src);
+    assert!(root.cancelled);
+    assert!(root.errors.is_empty());
+    let sentences = root
+        .into_syntax()
+        .children()
+        .filter(|n| n.kind() == SENTENCE)
+        .map(|n| n.text().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sentences,
+        vec!["{id:2,type:\"edge\"}", "{id:3,type:\"edge\"}"]
+    );
+}
+
+mod coords;
+
+#[test]
+fn test_semantic_tokens() {
+    use crate::semantic_tokens::{modifier, semantic_tokens, TokenType};
+    use crate::util::coords::Mapper;
+
+    let src = "{\"id\":1,\"type\":\"edge\",\"label\":\"next\",\"outV\":2,\"inV\":3}\n\
+               // comment\n\
+               {\"id\":4,\"start\":{\"line\":0},\"ok\":true,\"x\":\"é\"}";
+    let syntax = parser::parse(src).into_syntax();
+    let tokens = semantic_tokens(&syntax, &Mapper::new(src))
+        .data
+        .into_iter()
+        .map(|t| (t.delta_line, t.delta_start, t.length, t.token_type, t.token_modifiers_bitset))
+        .collect::<Vec<_>>();
+
+    let property = TokenType::Property as u32;
+    let label = TokenType::Label as u32;
+    let id = TokenType::Id as u32;
+    assert_eq!(
+        tokens,
+        vec![
+            (0, 1, 4, property, 0),
+            (0, 5, 1, id, modifier::DECLARATION),
+            (0, 2, 6, property, 0),
+            (0, 7, 6, label, 0),
+            (0, 7, 7, property, 0),
+            (0, 8, 6, label, 0),
+            (0, 7, 6, property, 0),
+            (0, 7, 1, id, 0),
+            (0, 2, 5, property, 0),
+            (0, 6, 1, id, 0),
+            (1, 0, 10, TokenType::Comment as u32, 0),
+            (1, 1, 4, property, 0),
+            (0, 5, 1, id, modifier::DECLARATION),
+            (0, 2, 7, property, 0),
+            (0, 9, 6, property, 0),
+            (0, 7, 1, TokenType::Number as u32, 0),
+            (0, 3, 4, property, 0),
+            (0, 5, 4, TokenType::Keyword as u32, 0),
+            (0, 5, 3, property, 0),
+            (0, 4, 3, TokenType::String as u32, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_semantic_tokens_trivia() {
+    use crate::semantic_tokens::{modifier, semantic_tokens, TokenType};
+    use crate::util::coords::{Mapper, PositionEncoding};
+
+    // keys with trivia, and tokens ending with a surrogate pair.
+    let src = "{ id : 1, label : \"next\", \"outV\" : 2 }\n\
+               // a😀\n\
+               {\"x\":\"é😀\"}";
+    let syntax = parser::parse(src).into_syntax();
+    let tokens = semantic_tokens(&syntax, &Mapper::with_encoding(src, PositionEncoding::Utf16))
+        .data
+        .into_iter()
+        .map(|t| (t.delta_line, t.delta_start, t.length, t.token_type, t.token_modifiers_bitset))
+        .collect::<Vec<_>>();
+
+    let property = TokenType::Property as u32;
+    let id = TokenType::Id as u32;
+    assert_eq!(
+        tokens,
+        vec![
+            (0, 2, 2, property, 0),
+            (0, 5, 1, id, modifier::DECLARATION),
+            (0, 3, 5, property, 0),
+            (0, 8, 6, TokenType::Label as u32, 0),
+            (0, 8, 6, property, 0),
+            (0, 9, 1, id, 0),
+            (1, 0, 6, TokenType::Comment as u32, 0),
+            (1, 1, 3, property, 0),
+            (0, 4, 5, TokenType::String as u32, 0),
+        ]
+    );
+}
@@ -0,0 +1,237 @@
+use crate::util::coords::{
+    CharacterOffset, CharacterRange, Mapper, Position, PositionEncoding, Range,
+};
+use proptest::prelude::*;
+use rowan::{TextRange, TextSize};
+
+/// The original `Mapper` that stored a character for every byte,
+/// used as a reference for the lookups it got right,
+/// counting characters in the units of an encoding.
+struct Reference {
+    lines: Vec<CharacterRange>,
+    mapping: Vec<CharacterOffset>,
+    /// The character after every byte, where ranges ending with it end.
+    ends: Vec<CharacterOffset>,
+}
+
+impl Reference {
+    fn with_encoding(source: &str, encoding: PositionEncoding) -> Self {
+        let mut line_start_char = 0;
+        let mut chars = 0;
+        let mut lines = Vec::new();
+        let mut mapping = Vec::new();
+        let mut ends = Vec::new();
+
+        for c in source.chars() {
+            let units = width(c.encode_utf8(&mut [0; 4]), encoding);
+            for byte in 0..c.len_utf8() as u64 {
+                // the bytes of characters that are one unit per byte
+                // are characters of their own.
+                if units == c.len_utf8() as u64 {
+                    mapping.push(chars + byte);
+                    ends.push(chars + byte + 1);
+                } else {
+                    mapping.push(chars);
+                    ends.push(chars + units);
+                }
+            }
+            chars += units;
+
+            if c == '\n' {
+                lines.push(line_start_char..chars);
+                line_start_char = chars;
+            }
+        }
+
+        lines.push(line_start_char..chars.max(line_start_char + 1));
+
+        Self {
+            lines,
+            mapping,
+            ends,
+        }
+    }
+
+    fn line(&self, c: CharacterOffset) -> Option<(usize, &CharacterRange)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .find(|(_, line)| line.start <= c && line.end > c)
+    }
+
+    fn position(&self, offset: TextSize) -> Option<Position> {
+        self.mapping.get(u32::from(offset) as usize).and_then(|c| {
+            self.line(*c).map(|(line_idx, line)| Position {
+                line: line_idx as u64,
+                character: *c - line.start,
+            })
+        })
+    }
+
+    fn range(&self, range: TextRange) -> Option<Range> {
+        let last = u32::from(
+            range
+                .end()
+                .checked_sub(TextSize::from(1))
+                .unwrap_or_default(),
+        ) as usize;
+        self.position(range.start()).and_then(|start| {
+            let c = *self.mapping.get(last)?;
+            self.line(c).map(|(line_idx, line)| Range {
+                start,
+                end: Position {
+                    line: line_idx as u64,
+                    character: self.ends[last] - line.start,
+                },
+            })
+        })
+    }
+}
+
+fn source() -> impl Strategy<Value = String> {
+    "[ab{}:,\" \n\r\té€😀]{0,64}"
+}
+
+proptest! {
+    #[test]
+    fn same_lines(s in source(), encoding in encodings()) {
+        let mapper = Mapper::with_encoding(&s, encoding);
+        prop_assert_eq!(mapper.lines(), &Reference::with_encoding(&s, encoding).lines[..]);
+    }
+
+    #[test]
+    fn same_positions(s in source(), encoding in encodings()) {
+        let mapper = Mapper::with_encoding(&s, encoding);
+        let reference = Reference::with_encoding(&s, encoding);
+        for offset in 0..=s.len() as u32 + 1 {
+            let offset = TextSize::from(offset);
+            prop_assert_eq!(
+                mapper.character(offset),
+                reference.mapping.get(u32::from(offset) as usize).copied()
+            );
+            prop_assert_eq!(mapper.position(offset), reference.position(offset));
+        }
+    }
+
+    #[test]
+    fn same_ranges(s in source(), a in 0u32..70, b in 0u32..70, encoding in encodings()) {
+        let mapper = Mapper::with_encoding(&s, encoding);
+        let reference = Reference::with_encoding(&s, encoding);
+        let range = TextRange::new(TextSize::from(a.min(b)), TextSize::from(a.max(b)));
+        if !range.is_empty() {
+            prop_assert_eq!(mapper.range(range), reference.range(range));
+        } else if let Some(r) = mapper.range(range) {
+            prop_assert_eq!(r.start.line, r.end.line);
+            prop_assert!(r.end.character - r.start.character <= 1);
+        }
+    }
+
+    #[test]
+    fn offset_round_trip(s in source()) {
+        let mapper = Mapper::new(&s);
+        for (offset, _) in s.char_indices() {
+            let offset = TextSize::from(offset as u32);
+            let position = mapper.position(offset).unwrap();
+            prop_assert_eq!(mapper.offset(position), Some(offset));
+        }
+        prop_assert_eq!(mapper.offset(mapper.end()), Some(TextSize::from(s.len() as u32)));
+    }
+}
+
+fn encodings() -> impl Strategy<Value = PositionEncoding> {
+    prop_oneof![
+        Just(PositionEncoding::Utf8),
+        Just(PositionEncoding::Utf16),
+        Just(PositionEncoding::Utf32),
+    ]
+}
+
+fn width(s: &str, encoding: PositionEncoding) -> u64 {
+    match encoding {
+        PositionEncoding::Utf8 => s.len() as u64,
+        PositionEncoding::Utf16 => s.encode_utf16().count() as u64,
+        PositionEncoding::Utf32 => s.chars().count() as u64,
+    }
+}
+
+#[test]
+fn encoded_positions() {
+    let src = "a😀é\n€b";
+    let b = TextSize::from(src.find('b').unwrap() as u32);
+    let e = TextSize::from(src.find('é').unwrap() as u32);
+
+    let utf8 = Mapper::with_encoding(src, PositionEncoding::Utf8);
+    assert_eq!(utf8.position(e), Some(Position::new(0, 5)));
+    assert_eq!(utf8.position(b), Some(Position::new(1, 3)));
+    let utf16 = Mapper::with_encoding(src, PositionEncoding::Utf16);
+    assert_eq!(utf16.position(e), Some(Position::new(0, 3)));
+    assert_eq!(utf16.position(b), Some(Position::new(1, 1)));
+    // the second half of the surrogate pair
+    assert_eq!(utf16.offset(Position::new(0, 2)), Some(TextSize::from(1)));
+    let utf32 = Mapper::new(src);
+    assert_eq!(utf32.encoding(), PositionEncoding::Utf32);
+    assert_eq!(utf32.position(e), Some(Position::new(0, 2)));
+    assert_eq!(utf32.position(b), Some(Position::new(1, 1)));
+
+    // ranges end after the width of their last character.
+    let emoji = TextRange::new(TextSize::from(0), TextSize::from(5));
+    assert_eq!(
+        utf8.range(emoji),
+        Some(Range::new(Position::new(0, 0), Position::new(0, 5)))
+    );
+    assert_eq!(
+        utf16.range(emoji),
+        Some(Range::new(Position::new(0, 0), Position::new(0, 3)))
+    );
+    assert_eq!(
+        utf32.range(emoji),
+        Some(Range::new(Position::new(0, 0), Position::new(0, 2)))
+    );
+
+    assert_eq!("utf-16".parse(), Ok(PositionEncoding::Utf16));
+    assert_eq!(PositionEncoding::Utf8.as_str(), "utf-8");
+}
+
+proptest! {
+    #[test]
+    fn encoded_round_trip(s in source(), encoding in encodings()) {
+        let mapper = Mapper::with_encoding(&s, encoding);
+        let mut line_start = 0;
+        for (offset, c) in s.char_indices() {
+            let position = mapper.position(TextSize::from(offset as u32)).unwrap();
+            prop_assert_eq!(position.character, width(&s[line_start..offset], encoding));
+            prop_assert_eq!(mapper.offset(position), Some(TextSize::from(offset as u32)));
+            if c == '\n' {
+                line_start = offset + 1;
+            }
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn incremental_edit(
+        s in source(),
+        new_text in source(),
+        a in 0usize..70,
+        b in 0usize..70,
+        encoding in encodings(),
+    ) {
+        let boundary = |i: usize| {
+            let mut i = i.min(s.len());
+            while !s.is_char_boundary(i) {
+                i -= 1;
+            }
+            i
+        };
+        let (start, end) = (boundary(a.min(b)), boundary(a.max(b)));
+        let edited = format!("{}{}{}", &s[..start], new_text, &s[end..]);
+
+        let mut mapper = Mapper::with_encoding(&s, encoding);
+        mapper.apply_edit(
+            TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32)),
+            &new_text,
+        );
+        prop_assert_eq!(mapper, Mapper::with_encoding(&edited, encoding));
+    }
+}
@@ -1,145 +1,367 @@
 //! Utilities for mapping between offset:length bytes and col:row character positions.
 
 use rowan::{TextRange, TextSize};
+use std::convert::TryFrom;
 
 pub use lsp_types::{Position, Range};
-use std::iter;
 
 /// Offset in characters instead of bytes.
+/// The characters are counted in the units of the [`PositionEncoding`]
+/// of the mapper.
 /// It is u64 because lsp_types uses u64.
 pub type CharacterOffset = u64;
 
 /// Offset range in characters instead of bytes.
 pub type CharacterRange = std::ops::Range<CharacterOffset>;
 
+/// The unit in which the characters of positions are counted,
+/// as negotiated by `positionEncoding` since LSP 3.17.
+///
+/// The LSP default, as well as the ranges of LSIF dumps, is UTF-16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PositionEncoding {
+    /// Bytes.
+    Utf8,
+    /// UTF-16 code units.
+    Utf16,
+    /// Unicode scalar values.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// The name of the encoding in LSP.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PositionEncoding::Utf8 => "utf-8",
+            PositionEncoding::Utf16 => "utf-16",
+            PositionEncoding::Utf32 => "utf-32",
+        }
+    }
+
+    /// The length of a character in this encoding.
+    fn width(self, c: char) -> u32 {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8() as u32,
+            PositionEncoding::Utf16 => c.len_utf16() as u32,
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+impl std::str::FromStr for PositionEncoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf-8" => Ok(PositionEncoding::Utf8),
+            "utf-16" => Ok(PositionEncoding::Utf16),
+            "utf-32" => Ok(PositionEncoding::Utf32),
+            _ => Err(()),
+        }
+    }
+}
+
 /// A mapper that translates offset:length bytes to
 /// col:row characters.
-#[derive(Debug, Clone)]
+///
+/// Only the lines and the characters that are not one unit per byte
+/// are stored, so the memory used is proportional to the line count and
+/// to the number of non-ASCII characters, not to the size of the source.
+/// All lookups are binary searches.
+///
+/// Byte offsets are [`TextSize`]s, so sources must be shorter than 4 GiB,
+/// the mapper panics otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Mapper {
+    encoding: PositionEncoding,
+
     /// These are characters, not byte offsets.
     lines: Vec<CharacterRange>,
 
-    /// A character position mapped to each byte offset.
-    /// If there was a single character that is 3 bytes long,
-    /// then this will contain 3 elements that are zero each.
-    mapping: Vec<CharacterOffset>,
+    /// All the characters whose length in bytes differs from their
+    /// width in the encoding, sorted by offset.
+    multibyte: Vec<MultiByteChar>,
+
+    /// The length of the source in bytes.
+    len: u32,
+
+    /// The length of the source in characters.
+    chars: CharacterOffset,
+}
+
+/// The length of a source in bytes, which must fit in a [`TextSize`].
+fn text_len(len: u64) -> u32 {
+    u32::try_from(len).expect("sources of 4 GiB or more are not supported")
+}
+
+/// A character that is more than one byte long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MultiByteChar {
+    /// The byte offset of the character.
+    offset: u32,
+    /// The length of the character in bytes.
+    len: u32,
+    /// The character offset of the character.
+    character: CharacterOffset,
+    /// The width of the character in the units of the encoding.
+    width: u32,
 }
 
 impl Mapper {
     /// Creates a new Mapper that remembers where
     /// each line starts and ends.
+    ///
+    /// Characters are counted as Unicode scalar values,
+    /// see [`Mapper::with_encoding`] for other encodings.
+    ///
+    /// Panics if the source is 4 GiB or longer.
     pub fn new(source: &str) -> Self {
-        let mut line_start_char = 0;
+        Self::with_encoding(source, PositionEncoding::Utf32)
+    }
 
-        let mut total_chars = 0;
+    /// Creates a new Mapper that counts characters
+    /// in the units of the given encoding.
+    ///
+    /// Panics if the source is 4 GiB or longer.
+    pub fn with_encoding(source: &str, encoding: PositionEncoding) -> Self {
+        // the offsets in the source fit in an u32 as well.
+        let len = text_len(source.len() as u64);
+        let mut line_start_char = 0;
 
         let mut lines = Vec::with_capacity(512); // a guess
-        let mut mapping = Vec::with_capacity(source.len() * 4); // We assume the worst case
+        let mut multibyte = Vec::new();
+        let mut chars = 0;
+
+        for (offset, c) in source.char_indices() {
+            let width = encoding.width(c);
+            if c.len_utf8() as u32 != width {
+                multibyte.push(MultiByteChar {
+                    offset: offset as u32,
+                    len: c.len_utf8() as u32,
+                    character: chars,
+                    width,
+                });
+            }
 
-        for (i, c) in source.chars().enumerate() {
-            mapping.extend(iter::repeat(i as u64).take(c.len_utf8()));
+            chars += width as CharacterOffset;
 
             if c == '\n' {
-                lines.push(line_start_char..i as u64 + 1);
-                line_start_char = i as u64 + 1;
+                lines.push(line_start_char..chars);
+                line_start_char = chars;
             }
-
-            total_chars = i as u64;
         }
 
-        if line_start_char < total_chars + 1 {
-            lines.push(line_start_char..total_chars as u64 + 1);
-        } else {
-            // last empty line
-            let last_mapping = mapping.last().copied().unwrap_or_default() + 1;
-            lines.push(last_mapping..last_mapping + 1);
+        // the last line, or an empty line after the last line break.
+        lines.push(line_start_char..chars.max(line_start_char + 1));
+
+        Self {
+            encoding,
+            lines,
+            multibyte,
+            len,
+            chars,
         }
+    }
 
-        Self { lines, mapping }
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
     }
 
     pub fn lines(&self) -> &[CharacterRange] {
         &self.lines
     }
 
-    pub fn mapping(&self) -> &[CharacterOffset] {
-        &self.mapping
+    /// The character at the given byte offset,
+    /// or `None` if it is out of the source.
+    pub fn character(&self, offset: TextSize) -> Option<CharacterOffset> {
+        let offset = u32::from(offset);
+        if offset >= self.len {
+            return None;
+        }
+
+        let idx = self.multibyte.partition_point(|m| m.offset <= offset);
+        Some(match idx.checked_sub(1).map(|i| self.multibyte[i]) {
+            None => offset as CharacterOffset,
+            Some(m) if offset < m.offset + m.len => m.character,
+            Some(m) => {
+                m.character + m.width as CharacterOffset + (offset - m.offset - m.len) as CharacterOffset
+            }
+        })
     }
 
-    pub fn offset(&self, position: Position) -> Option<TextSize> {
-        self.lines().get(position.line as usize).map(|l| {
-            let idx = (l.start + position.character)
-                .checked_sub(1)
-                .unwrap_or_default();
-
-            self.mapping
-                .iter()
-                .enumerate()
-                .find_map(|(i, p)| {
-                    if *p == idx {
-                        Some(TextSize::from(i as u32))
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or_else(||TextSize::from(self.mapping.len() as u32)) // Last empty line
+    /// The width in the units of the encoding of the character
+    /// at the given byte offset, which must be in the source.
+    ///
+    /// Characters that are one unit per byte are not stored, so in the middle
+    /// of a character the width of the byte is returned, as [`Mapper::character`]
+    /// counts bytes there as well.
+    fn width(&self, offset: TextSize) -> u32 {
+        let offset = u32::from(offset);
+        let idx = self.multibyte.partition_point(|m| m.offset <= offset);
+        match idx.checked_sub(1).map(|i| self.multibyte[i]) {
+            Some(m) if offset < m.offset + m.len => m.width,
+            _ => 1,
+        }
+    }
+
+    /// The byte offset of the given character, or the length of
+    /// the source if it is out of the source.
+    ///
+    /// A character in the middle of a wide one (e.g. the second half of a
+    /// surrogate pair) is mapped to the start of the wide character.
+    fn byte_offset(&self, character: CharacterOffset) -> TextSize {
+        if character >= self.chars {
+            return TextSize::from(self.len);
+        }
+
+        let idx = self.multibyte.partition_point(|m| m.character <= character);
+        TextSize::from(match idx.checked_sub(1).map(|i| self.multibyte[i]) {
+            None => character as u32,
+            Some(m) if character < m.character + m.width as CharacterOffset => m.offset,
+            Some(m) => m.offset + m.len + (character - m.character - m.width as CharacterOffset) as u32,
         })
     }
 
+    /// The index of the line that contains the given character.
+    fn line_index(&self, character: CharacterOffset) -> Option<usize> {
+        let idx = self.lines.partition_point(|l| l.end <= character);
+        self.lines
+            .get(idx)
+            .filter(|l| l.start <= character)
+            .map(|_| idx)
+    }
+
+    /// Updates the mapper after the text in `range` was replaced by `new_text`,
+    /// as if it was created again from the edited source.
+    ///
+    /// Only the lines touched by the edit are computed again,
+    /// the ones after it are shifted. The range is in bytes of the source
+    /// before the edit, use [`Mapper::text_range`] to convert an LSP range.
+    ///
+    /// Panics if the edited source is 4 GiB or longer.
+    pub fn apply_edit(&mut self, range: TextRange, new_text: &str) {
+        let start = u32::from(range.start()).min(self.len);
+        let end = u32::from(range.end()).min(self.len);
+        // the offsets in the new text fit in an u32 as well.
+        let len = text_len(u64::from(self.len - (end - start)) + new_text.len() as u64);
+
+        let start_char = self.character(TextSize::from(start)).unwrap_or(self.chars);
+        let end_char = self.character(TextSize::from(end)).unwrap_or(self.chars);
+        let last_line = self.lines.len() - 1;
+        let first = self.line_index(start_char).unwrap_or(last_line);
+        let last = self.line_index(end_char).unwrap_or(last_line);
+
+        // the characters of the new text.
+        let mut new_lines = Vec::new();
+        let mut new_multibyte = Vec::new();
+        let mut line_start_char = self.lines[first].start;
+        let mut chars = start_char;
+        for (offset, c) in new_text.char_indices() {
+            let width = self.encoding.width(c);
+            if c.len_utf8() as u32 != width {
+                new_multibyte.push(MultiByteChar {
+                    offset: start + offset as u32,
+                    len: c.len_utf8() as u32,
+                    character: chars,
+                    width,
+                });
+            }
+
+            chars += width as CharacterOffset;
+
+            if c == '\n' {
+                new_lines.push(line_start_char..chars);
+                line_start_char = chars;
+            }
+        }
+
+        let byte_delta = new_text.len() as i64 - (end - start) as i64;
+        let char_delta = chars as i64 - end_char as i64;
+        let shift = |c: CharacterOffset| (c as i64 + char_delta) as CharacterOffset;
+
+        self.len = len;
+        self.chars = shift(self.chars);
+
+        // the line the edit ends in continues after the new text.
+        if last == last_line {
+            new_lines.push(line_start_char..self.chars.max(line_start_char + 1));
+        } else {
+            new_lines.push(line_start_char..shift(self.lines[last].end));
+        }
+        for line in &mut self.lines[last + 1..] {
+            *line = shift(line.start)..shift(line.end);
+        }
+        self.lines.splice(first..=last, new_lines);
+
+        let removed_start = self.multibyte.partition_point(|m| m.offset < start);
+        let removed_end = self.multibyte.partition_point(|m| m.offset < end);
+        for m in &mut self.multibyte[removed_end..] {
+            m.offset = (m.offset as i64 + byte_delta) as u32;
+            m.character = shift(m.character);
+        }
+        self.multibyte.splice(removed_start..removed_end, new_multibyte);
+    }
+
+    pub fn offset(&self, position: Position) -> Option<TextSize> {
+        self.lines()
+            .get(position.line as usize)
+            .map(|l| self.byte_offset(l.start + position.character))
+    }
+
     pub fn text_range(&self, range: Range) -> Option<TextRange> {
         self.offset(range.start)
             .and_then(|start| self.offset(range.end).map(|end| TextRange::new(start, end)))
     }
 
     pub fn position(&self, offset: TextSize) -> Option<Position> {
-        self.mapping.get(u32::from(offset) as usize).and_then(|c| {
-            self.lines
-                .iter()
-                .enumerate()
-                .find(|(_, line)| line.start <= *c && line.end > *c)
-                .map(|(line_idx, line)| Position {
-                    line: line_idx as u64,
-                    character: *c - line.start,
-                })
+        self.character(offset).and_then(|c| {
+            self.line_index(c).map(|line_idx| Position {
+                line: line_idx as u64,
+                character: c - self.lines[line_idx].start,
+            })
         })
     }
 
     /// A convenience method for finding range endings.
     /// Since ranges are exclusive, one must be subtracted for endings.
+    ///
+    /// This is the position of the start of the last character,
+    /// [`Mapper::range`] adds its width to it.
     pub fn position_end(&self, offset: TextSize) -> Option<Position> {
         self.position(offset.checked_sub(TextSize::from(1)).unwrap_or_default())
     }
 
     pub fn range(&self, range: TextRange) -> Option<Range> {
-        // Special case for a 0-length range
+        // Special case for a 0-length range,
+        // the character before it is used instead.
         if range.start() == range.end() {
-            return self
-                .mapping
-                .get(u32::from(range.start()) as usize)
-                .or_else(|| self.mapping.last())
-                .and_then(|c| {
-                    self.lines().iter().enumerate().find_map(|(i, l)| {
-                        if l.start <= *c && l.end >= *c {
-                            Some(Range {
-                                start: Position {
-                                    line: i as u64,
-                                    character: (*c).checked_sub(1).unwrap_or_default(),
-                                },
-                                end: Position {
-                                    line: i as u64,
-                                    character: *c,
-                                },
-                            })
-                        } else {
-                            None
-                        }
-                    })
-                });
+            let c = self.character(range.start()).or_else(|| {
+                self.len
+                    .checked_sub(1)
+                    .and_then(|last| self.character(TextSize::from(last)))
+            })?;
+            let i = self.lines.partition_point(|l| l.end < c);
+            let line = self.lines.get(i).filter(|l| l.start <= c)?;
+            let character = c - line.start;
+
+            return Some(Range {
+                start: Position {
+                    line: i as u64,
+                    character: character.saturating_sub(1),
+                },
+                end: Position {
+                    line: i as u64,
+                    character,
+                },
+            });
         }
 
         self.position(range.start()).and_then(|start| {
             self.position_end(range.end()).map(|mut end| {
-                end.character += 1;
+                // the end is after the last character, which can be
+                // more than one unit wide.
+                let last = range.end() - TextSize::from(1);
+                end.character += self.width(last) as CharacterOffset;
                 Range { start, end }
             })
         })
@@ -199,7 +421,7 @@ impl Mapper {
 
     pub fn end(&self) -> Position {
         Position {
-            line: (self.lines.len() as u64).checked_sub(1).unwrap_or_default(),
+            line: (self.lines.len() as u64).saturating_sub(1),
             character: self
                 .lines
                 .last()
@@ -1,4 +1,5 @@
 use logos::{Lexer, Logos};
+use std::borrow::Cow;
 
 /// Escaping based on:
 ///
@@ -94,6 +95,21 @@ pub fn unescape(s: &str) -> Result<String, usize> {
     Ok(new_s + lexer.remainder())
 }
 
+/// The text of a key or string token without its quotes, unescaped.
+///
+/// Only text with a `\` is copied, and text with invalid escapes
+/// is returned as it is.
+pub fn unquote(s: &str) -> Cow<'_, str> {
+    let stripped = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s);
+    if !stripped.contains('\\') {
+        return Cow::Borrowed(stripped);
+    }
+    unescape(stripped).map_or(Cow::Borrowed(stripped), Cow::Owned)
+}
+
 /// Same as unescape, but doesn't create a new
 /// unescaped string, and returns all invalid escape indices.
 pub fn check_escape(s: &str) -> Result<(), Vec<usize>> {
@@ -119,12 +135,9 @@ pub fn check_escape(s: &str) -> Result<(), Vec<usize>> {
                     }
                 };
 
-                match std::char::from_u32(char_val) {
-                    None => {
-                        invalid.push(lexer.span().start);
-                    }
-                    Some(_) => {}
-                };
+                if std::char::from_u32(char_val).is_none() {
+                    invalid.push(lexer.span().start);
+                }
             }
             UnicodeLarge => {
                 let char_val = match u32::from_str_radix(&lexer.slice()[2..], 16) {
@@ -135,12 +148,9 @@ pub fn check_escape(s: &str) -> Result<(), Vec<usize>> {
                     }
                 };
 
-                match std::char::from_u32(char_val) {
-                    None => {
-                        invalid.push(lexer.span().start);
-                    }
-                    Some(_) => {}
-                };
+                if std::char::from_u32(char_val).is_none() {
+                    invalid.push(lexer.span().start);
+                }
             }
             Unknown => invalid.push(lexer.span().start),
             UnEscaped => {}
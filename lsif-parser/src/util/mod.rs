@@ -1,9 +1,11 @@
 pub mod coords;
+pub mod scan;
 mod escape;
 
 use crate::syntax::{SyntaxElement, SyntaxKind, SyntaxNode};
 pub use escape::check_escape;
 pub use escape::unescape;
+pub use escape::unquote;
 use rowan::TextSize;
 
 pub(crate) mod allowed_chars {
@@ -28,9 +30,9 @@ pub(crate) mod allowed_chars {
 
         for (i, c) in s.chars().enumerate() {
             if c != '\t'
-                && (c >= '\u{0000}' && c <= '\u{0008}'
-                || c >= '\u{000A}' && c <= '\u{001F}'
-                || c == '\u{007F}')
+                && (('\u{0000}'..='\u{0008}').contains(&c)
+                    || ('\u{000A}'..='\u{001F}').contains(&c)
+                    || c == '\u{007F}')
             {
                 err_indices.push(i);
             }
@@ -51,19 +53,11 @@ pub trait StringExt {
 
 impl StringExt for &str {
     fn remove_prefix<'a>(&'a self, p: &str) -> &'a str {
-        if self.starts_with(p) {
-            &self[p.len()..]
-        } else {
-            self
-        }
+        self.strip_prefix(p).unwrap_or(self)
     }
 
     fn remove_suffix<'a>(&'a self, p: &str) -> &'a str {
-        if self.ends_with(p) {
-            &self[..self.len() - p.len()]
-        } else {
-            self
-        }
+        self.strip_suffix(p).unwrap_or(self)
     }
 }
 
@@ -105,12 +99,6 @@ impl SyntaxExt for SyntaxNode {
     }
 
     fn find(&self, kind: SyntaxKind) -> Option<SyntaxElement> {
-        for d in self.descendants_with_tokens() {
-            if d.kind() == kind {
-                return Some(d);
-            }
-        }
-
-        None
+        self.descendants_with_tokens().find(|d| d.kind() == kind)
     }
 }
\ No newline at end of file
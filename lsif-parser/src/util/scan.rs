@@ -0,0 +1,210 @@
+//! A fast structural pre-scan of LSIF dumps.
+//!
+//! The pre-scan finds the line boundaries and the positions of the
+//! top-level `id`, `type` and `label` values of each line without running
+//! the lexer. Line ends and string contents, which make most of a dump,
+//! are skipped with the SIMD-accelerated searches of `memchr`, and a line
+//! is not scanned any further once all three fields were found.
+//!
+//! It does not validate anything, the syntax errors are reported by the parser.
+
+use memchr::{memchr, memchr2};
+use rowan::{TextRange, TextSize};
+
+/// The result of the pre-scan of a single line.
+///
+/// The ranges of the values include the quotes of strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Line {
+    /// The range of the line without the line break.
+    pub range: TextRange,
+    pub id: Option<TextRange>,
+    pub type_: Option<TextRange>,
+    pub label: Option<TextRange>,
+}
+
+impl Line {
+    /// The text of the `id` value, without quotes.
+    pub fn id<'s>(&self, source: &'s str) -> Option<&'s str> {
+        self.id.map(|r| value_text(source, r))
+    }
+
+    /// The text of the `type` value, without quotes.
+    pub fn type_<'s>(&self, source: &'s str) -> Option<&'s str> {
+        self.type_.map(|r| value_text(source, r))
+    }
+
+    /// The text of the `label` value, without quotes.
+    pub fn label<'s>(&self, source: &'s str) -> Option<&'s str> {
+        self.label.map(|r| value_text(source, r))
+    }
+}
+
+fn value_text(source: &str, range: TextRange) -> &str {
+    let s = &source[range];
+    if !s.starts_with('"') {
+        return s;
+    }
+    // a truncated string has no closing quote.
+    match string_end(s.as_bytes(), 0, s.len()) {
+        (_, true) => &s[1..s.len() - 1],
+        (_, false) => &s[1..],
+    }
+}
+
+/// Pre-scan every line of the source.
+pub fn lines(source: &str) -> Lines<'_> {
+    Lines {
+        source: source.as_bytes(),
+        offset: 0,
+    }
+}
+
+/// An iterator over the pre-scanned lines of a source,
+/// created by [`lines`].
+#[derive(Debug, Clone)]
+pub struct Lines<'s> {
+    source: &'s [u8],
+    offset: usize,
+}
+
+impl Iterator for Lines<'_> {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        if self.offset >= self.source.len() {
+            return None;
+        }
+
+        let start = self.offset;
+        let rest = &self.source[start..];
+        let (mut end, next) = match memchr(b'\n', rest) {
+            Some(i) => (start + i, start + i + 1),
+            None => (self.source.len(), self.source.len()),
+        };
+        self.offset = next;
+        if end > start && self.source[end - 1] == b'\r' {
+            end -= 1;
+        }
+
+        let mut line = Line {
+            range: range(start, end),
+            id: None,
+            type_: None,
+            label: None,
+        };
+        scan_line(self.source, start, end, &mut line);
+        Some(line)
+    }
+}
+
+fn range(start: usize, end: usize) -> TextRange {
+    TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32))
+}
+
+/// Returns the end of the string starting at `start`, after the closing quote,
+/// and whether it was closed before `end`.
+fn string_end(bytes: &[u8], start: usize, end: usize) -> (usize, bool) {
+    let mut i = start + 1;
+    while let Some(found) = memchr2(b'"', b'\\', &bytes[i..end]) {
+        if bytes[i + found] == b'\\' {
+            i += found + 2;
+            if i >= end {
+                return (end, false);
+            }
+        } else {
+            return (i + found + 1, true);
+        }
+    }
+    (end, false)
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize, end: usize) -> usize {
+    while i < end && (bytes[i] == b' ' || bytes[i] == b'\t') {
+        i += 1;
+    }
+    i
+}
+
+/// Returns the end of the scalar value starting at `start`.
+fn scalar_end(bytes: &[u8], start: usize, end: usize) -> usize {
+    if bytes[start] == b'"' {
+        return string_end(bytes, start, end).0;
+    }
+    let mut i = start;
+    while i < end && !matches!(bytes[i], b',' | b'}' | b']' | b' ' | b'\t') {
+        i += 1;
+    }
+    i
+}
+
+fn scan_line(bytes: &[u8], start: usize, end: usize, line: &mut Line) {
+    let mut depth = 0u32;
+    let mut expect_key = false;
+    let mut i = start;
+
+    while i < end {
+        let (key, key_end) = match bytes[i] {
+            b'"' => {
+                let (s, closed) = string_end(bytes, i, end);
+                if !(depth == 1 && expect_key) {
+                    i = s;
+                    continue;
+                }
+                // a truncated key goes up to the end of the line.
+                (&bytes[i + 1..if closed { s - 1 } else { s }], s)
+            }
+            b'A'..=b'Z' | b'a'..=b'z' if depth == 1 && expect_key => {
+                let mut s = i;
+                while s < end && bytes[s].is_ascii_alphabetic() {
+                    s += 1;
+                }
+                (&bytes[i..s], s)
+            }
+            b'{' | b'[' => {
+                depth += 1;
+                expect_key = depth == 1 && bytes[i] == b'{';
+                i += 1;
+                continue;
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+                continue;
+            }
+            b',' => {
+                expect_key = depth == 1;
+                i += 1;
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        expect_key = false;
+        i = skip_whitespace(bytes, key_end, end);
+        if i >= end || bytes[i] != b':' {
+            continue;
+        }
+        i = skip_whitespace(bytes, i + 1, end);
+        if i >= end {
+            break;
+        }
+
+        let field = match key {
+            b"id" => &mut line.id,
+            b"type" => &mut line.type_,
+            b"label" => &mut line.label,
+            _ => continue,
+        };
+        let value_end = scalar_end(bytes, i, end);
+        if field.is_none() && !matches!(bytes[i], b'{' | b'[') {
+            *field = Some(range(i, value_end));
+        }
+        if line.id.is_some() && line.type_.is_some() && line.label.is_some() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Writing dumps, for indexers.
+//!
+//! An [`Emitter`] allocates the ids of the elements, and writes them
+//! one per line. It refuses the elements that would make the dump
+//! invalid, before writing them:
+//!
+//! - The `metaData` vertex must be the first element, and the only one.
+//! - Edges can only connect vertices that were emitted before.
+//! - `$event` vertices must be nested like brackets, and be ended
+//!   before the dump is finished.
+//! - Nothing can be added to a document or a project after its end event.
+//!
+//! ```
+//! # use lsif_type::{emitter::Emitter, *};
+//! let mut emitter = Emitter::new(Vec::new());
+//! emitter.emit_vertex(Vertex::MetaData(MetaData {
+//!     version: "0.6.0".into(),
+//!     position_encoding: "utf-16".into(),
+//!     project_root: None,
+//!     tool_info: None,
+//! }))?;
+//! let document = emitter.emit_vertex(Vertex::Document(Document {
+//!     uri: Url::parse("file:///a.rs").unwrap(),
+//!     language_id: "rust".into(),
+//!     contents: None,
+//! }))?;
+//! emitter.begin(EventScope::Document, document.clone())?;
+//! let range = Range::new(Position::new(0, 3), Position::new(0, 7));
+//! emitter.emit_range_with_definition(document.clone(), range, None)?;
+//! emitter.end(EventScope::Document, document)?;
+//! let dump = emitter.finish()?;
+//! # Ok::<(), lsif_type::emitter::Error>(())
+//! ```
+
+use crate::{
+    Edge, EdgeData, Element, Entry, Event, EventKind, EventScope, Id, Item, MultiEdgeData,
+    ResultSet, Vertex,
+};
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{self, Write},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// An element before the `metaData` vertex.
+    MissingMetaData,
+    /// A `metaData` vertex after the first element.
+    DuplicatedMetaData,
+    /// An edge, or an event, referencing a vertex that was not emitted.
+    UnknownVertex {
+        id: Id,
+    },
+    /// An end event that does not end the innermost open scope.
+    UnbalancedEvent {
+        scope: EventScope,
+        data: Id,
+    },
+    /// The dump was finished with open scopes.
+    UnendedEvent {
+        scope: EventScope,
+        data: Id,
+    },
+    /// An element added to a document or a project after its end event.
+    ElementAfterEnd {
+        data: Id,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Json(e) => e.fmt(f),
+            Error::MissingMetaData => f.write_str("the first element must be a metaData vertex"),
+            Error::DuplicatedMetaData => f.write_str("a dump has only one metaData vertex"),
+            Error::UnknownVertex { id } => write!(f, "vertex {} was not emitted", id),
+            Error::UnbalancedEvent { scope, data } => write!(
+                f,
+                "the end of {:?} {} does not match the last begin event",
+                scope, data
+            ),
+            Error::UnendedEvent { scope, data } => {
+                write!(f, "{:?} {} began, but never ended", scope, data)
+            }
+            Error::ElementAfterEnd { data } => {
+                write!(f, "cannot add elements to {} after its end event", data)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+/// The ids of the elements of a range and its definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    pub range: Id,
+    pub result_set: Id,
+    pub definition_result: Id,
+    pub hover_result: Option<Id>,
+}
+
+/// Writes the elements of a dump, one per line.
+#[derive(Debug)]
+pub struct Emitter<W> {
+    writer: W,
+    last_id: u64,
+    vertices: HashSet<Id>,
+    /// The open scopes, the innermost last.
+    scopes: Vec<(EventScope, Id)>,
+    /// The documents and projects that ended.
+    ended: HashSet<Id>,
+}
+
+impl<W: Write> Emitter<W> {
+    pub fn new(writer: W) -> Self {
+        Emitter {
+            writer,
+            last_id: 0,
+            vertices: HashSet::new(),
+            scopes: Vec::new(),
+            ended: HashSet::new(),
+        }
+    }
+
+    /// Write a vertex, and return its id.
+    ///
+    /// `$event` vertices open and close scopes, as [`begin`](Self::begin)
+    /// and [`end`](Self::end) do.
+    pub fn emit_vertex(&mut self, vertex: Vertex) -> Result<Id, Error> {
+        match (&vertex, self.last_id) {
+            (Vertex::MetaData(_), 0) => {}
+            (Vertex::MetaData(_), _) => return Err(Error::DuplicatedMetaData),
+            (_, 0) => return Err(Error::MissingMetaData),
+            (Vertex::Event(event), _) => self.check_event(event)?,
+            _ => {}
+        }
+        let event = match &vertex {
+            Vertex::Event(event) => Some((event.kind, event.scope, event.data.clone())),
+            _ => None,
+        };
+
+        let id = self.write(Element::Vertex(vertex))?;
+        self.vertices.insert(id.clone());
+        match event {
+            Some((EventKind::Begin, scope, data)) => self.scopes.push((scope, data)),
+            Some((EventKind::End, _, data)) => {
+                self.scopes.pop();
+                self.ended.insert(data);
+            }
+            None => {}
+        }
+        Ok(id)
+    }
+
+    /// Write an edge between vertices that were emitted, and return its id.
+    pub fn emit_edge(&mut self, edge: Edge) -> Result<Id, Error> {
+        if self.last_id == 0 {
+            return Err(Error::MissingMetaData);
+        }
+        let out_v = edge.out_v();
+        for id in std::iter::once(out_v).chain(edge.in_vs()) {
+            if !self.vertices.contains(id) {
+                return Err(Error::UnknownVertex { id: id.clone() });
+            }
+        }
+        let owner = match &edge {
+            Edge::Contains(_) => Some(out_v),
+            Edge::Item(item) => Some(&item.shard),
+            _ => None,
+        };
+        if let Some(data) = owner.filter(|o| self.ended.contains(o)) {
+            return Err(Error::ElementAfterEnd { data: data.clone() });
+        }
+
+        self.write(Element::Edge(edge))
+    }
+
+    /// Begin the content of a project or a document.
+    pub fn begin(&mut self, scope: EventScope, data: Id) -> Result<Id, Error> {
+        self.emit_vertex(Vertex::Event(Event {
+            kind: EventKind::Begin,
+            scope,
+            data,
+        }))
+    }
+
+    /// End the innermost open scope, which must be the given one.
+    pub fn end(&mut self, scope: EventScope, data: Id) -> Result<Id, Error> {
+        self.emit_vertex(Vertex::Event(Event {
+            kind: EventKind::End,
+            scope,
+            data,
+        }))
+    }
+
+    /// Write a range of the document, with its result set, its definition,
+    /// and optionally its hover:
+    ///
+    /// ```text
+    /// document -contains-> range -next-> resultSet -textDocument/definition-> definitionResult
+    /// definitionResult -item-> range
+    /// resultSet -textDocument/hover-> hoverResult
+    /// ```
+    ///
+    /// The ranges of references can then be linked to the result set
+    /// with `next` edges.
+    pub fn emit_range_with_definition(
+        &mut self,
+        document: Id,
+        range: lsp::Range,
+        hover: Option<lsp::Hover>,
+    ) -> Result<Definition, Error> {
+        if !self.vertices.contains(&document) {
+            return Err(Error::UnknownVertex { id: document });
+        }
+        if self.ended.contains(&document) {
+            return Err(Error::ElementAfterEnd { data: document });
+        }
+
+        let range_id = self.emit_vertex(Vertex::Range { range, tag: None })?;
+        self.emit_edge(Edge::Contains(MultiEdgeData {
+            in_vs: vec![range_id.clone()],
+            out_v: document.clone(),
+        }))?;
+        let result_set = self.emit_vertex(Vertex::ResultSet(ResultSet { key: None }))?;
+        self.emit_edge(Edge::Next(EdgeData {
+            in_v: result_set.clone(),
+            out_v: range_id.clone(),
+        }))?;
+
+        let definition_result = self.emit_vertex(Vertex::DefinitionResult)?;
+        self.emit_edge(Edge::Definition(EdgeData {
+            in_v: definition_result.clone(),
+            out_v: result_set.clone(),
+        }))?;
+        self.emit_edge(Edge::Item(Item {
+            shard: document,
+            property: None,
+            edge_data: MultiEdgeData {
+                in_vs: vec![range_id.clone()],
+                out_v: definition_result.clone(),
+            },
+        }))?;
+
+        let hover_result = match hover {
+            Some(hover) => {
+                let hover_result = self.emit_vertex(Vertex::HoverResult {
+                    result: Some(hover),
+                })?;
+                self.emit_edge(Edge::Hover(EdgeData {
+                    in_v: hover_result.clone(),
+                    out_v: result_set.clone(),
+                }))?;
+                Some(hover_result)
+            }
+            None => None,
+        };
+
+        Ok(Definition {
+            range: range_id,
+            result_set,
+            definition_result,
+            hover_result,
+        })
+    }
+
+    /// End the dump, and return the writer, once flushed.
+    pub fn finish(mut self) -> Result<W, Error> {
+        if let Some((scope, data)) = self.scopes.first() {
+            return Err(Error::UnendedEvent {
+                scope: *scope,
+                data: data.clone(),
+            });
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn check_event(&self, event: &Event) -> Result<(), Error> {
+        let (scope, data) = (event.scope, event.data.clone());
+        if !self.vertices.contains(&data) {
+            return Err(Error::UnknownVertex { id: data });
+        }
+        match event.kind {
+            EventKind::Begin if self.ended.contains(&data) => Err(Error::ElementAfterEnd { data }),
+            EventKind::End if self.scopes.last().map(|(s, d)| (*s, d)) != Some((scope, &data)) => {
+                Err(Error::UnbalancedEvent { scope, data })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&mut self, data: Element) -> Result<Id, Error> {
+        let entry = Entry {
+            id: Id::from(self.last_id + 1),
+            data,
+        };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+        self.last_id += 1;
+        Ok(entry.id)
+    }
+}
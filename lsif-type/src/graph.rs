@@ -0,0 +1,181 @@
+//! An in-memory graph of a dump.
+//!
+//! The edges are indexed by their vertices and labels, so that following
+//! the edges of a vertex takes a time proportional to their number:
+//!
+//! ```
+//! # use lsif_type::{graph::Graph, version, Id};
+//! # let dump = include_str!("tests/lsif-0.6.lsif");
+//! let graph: Graph = version::upgrade(dump)?.into_iter().collect();
+//! let document = Id::from(6);
+//! for range in graph.out_vertices(&document, "contains") {
+//!     let result_set = graph.next(range);
+//! }
+//! # Ok::<(), version::Error>(())
+//! ```
+
+use crate::{Document, Edge, Element, Entry, Id, Item, Vertex};
+use std::{collections::HashMap, iter::FromIterator};
+
+/// The vertices and edges of a dump, by id.
+///
+/// Edges can be added before their vertices, as long as
+/// all of them are added before the graph is queried.
+#[derive(Debug, Default)]
+pub struct Graph {
+    vertices: HashMap<Id, Vertex>,
+    edges: HashMap<Id, Edge>,
+    /// The edges out of a vertex, by label.
+    out_edges: HashMap<(Id, &'static str), Vec<Id>>,
+    /// The edges into a vertex, by label.
+    in_edges: HashMap<(Id, &'static str), Vec<Id>>,
+    /// The `item` edges of a result, by document.
+    items: HashMap<(Id, Id), Vec<Id>>,
+    documents: HashMap<lsp::Url, Id>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, entry: Entry) {
+        match entry.data {
+            Element::Vertex(vertex) => {
+                if let Vertex::Document(Document { uri, .. }) = &vertex {
+                    self.documents.insert(uri.clone(), entry.id.clone());
+                }
+                self.vertices.insert(entry.id, vertex);
+            }
+            Element::Edge(edge) => {
+                let label = edge.label();
+                self.out_edges
+                    .entry((edge.out_v().clone(), label))
+                    .or_default()
+                    .push(entry.id.clone());
+                for in_v in edge.in_vs() {
+                    self.in_edges
+                        .entry((in_v.clone(), label))
+                        .or_default()
+                        .push(entry.id.clone());
+                }
+                if let Edge::Item(item) = &edge {
+                    self.items
+                        .entry((item.edge_data.out_v.clone(), item.shard.clone()))
+                        .or_default()
+                        .push(entry.id.clone());
+                }
+                self.edges.insert(entry.id, edge);
+            }
+        }
+    }
+
+    pub fn vertex(&self, id: &Id) -> Option<&Vertex> {
+        self.vertices.get(id)
+    }
+
+    pub fn edge(&self, id: &Id) -> Option<&Edge> {
+        self.edges.get(id)
+    }
+
+    pub fn vertices(&self) -> impl Iterator<Item = (&Id, &Vertex)> {
+        self.vertices.iter()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (&Id, &Edge)> {
+        self.edges.iter()
+    }
+
+    /// The document vertex of a URI.
+    pub fn document(&self, uri: &lsp::Url) -> Option<&Id> {
+        self.documents.get(uri)
+    }
+
+    /// The edges with the label out of the vertex, in the order of the dump.
+    pub fn out_edges<'a>(
+        &'a self,
+        vertex: &Id,
+        label: &'static str,
+    ) -> impl Iterator<Item = &'a Edge> {
+        self.edges_of(&self.out_edges, vertex, label)
+    }
+
+    /// The edges with the label into the vertex, in the order of the dump.
+    pub fn in_edges<'a>(
+        &'a self,
+        vertex: &Id,
+        label: &'static str,
+    ) -> impl Iterator<Item = &'a Edge> {
+        self.edges_of(&self.in_edges, vertex, label)
+    }
+
+    /// The in vertices of the edges with the label out of the vertex.
+    pub fn out_vertices<'a>(
+        &'a self,
+        vertex: &Id,
+        label: &'static str,
+    ) -> impl Iterator<Item = &'a Id> {
+        self.out_edges(vertex, label).flat_map(Edge::in_vs)
+    }
+
+    /// The out vertices of the edges with the label into the vertex.
+    pub fn in_vertices<'a>(
+        &'a self,
+        vertex: &Id,
+        label: &'static str,
+    ) -> impl Iterator<Item = &'a Id> {
+        self.in_edges(vertex, label).map(Edge::out_v)
+    }
+
+    /// The vertex the `next` edge of a range or a result set points to.
+    pub fn next(&self, vertex: &Id) -> Option<&Id> {
+        self.out_vertices(vertex, "next").next()
+    }
+
+    /// The `item` edges of a result for the ranges of a document.
+    pub fn items(&self, result: &Id, document: &Id) -> impl Iterator<Item = &Item> {
+        let ids = self.items.get(&(result.clone(), document.clone()));
+        ids.into_iter()
+            .flatten()
+            .filter_map(move |id| match self.edges.get(id) {
+                Some(Edge::Item(item)) => Some(item),
+                _ => None,
+            })
+    }
+
+    /// The `item` edges of a result, for all the documents.
+    pub fn all_items(&self, result: &Id) -> impl Iterator<Item = &Item> {
+        self.out_edges(result, "item").filter_map(|e| match e {
+            Edge::Item(item) => Some(item),
+            _ => None,
+        })
+    }
+
+    fn edges_of<'a>(
+        &'a self,
+        index: &'a HashMap<(Id, &'static str), Vec<Id>>,
+        vertex: &Id,
+        label: &'static str,
+    ) -> impl Iterator<Item = &'a Edge> {
+        let ids = index.get(&(vertex.clone(), label));
+        ids.into_iter()
+            .flatten()
+            .filter_map(move |id| self.edges.get(id))
+    }
+}
+
+impl Extend<Entry> for Graph {
+    fn extend<T: IntoIterator<Item = Entry>>(&mut self, entries: T) {
+        for entry in entries {
+            self.insert(entry);
+        }
+    }
+}
+
+impl FromIterator<Entry> for Graph {
+    fn from_iter<T: IntoIterator<Item = Entry>>(entries: T) -> Self {
+        let mut graph = Graph::new();
+        graph.extend(entries);
+        graph
+    }
+}
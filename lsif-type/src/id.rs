@@ -0,0 +1,123 @@
+//! The ids of vertices and edges.
+//!
+//! Ids are numbers or strings. Numbers are stored inline, and string
+//! ids are shared: cloning an id never copies its string, and the string
+//! is freed with the last id that refers to it, so the ids of a dump are
+//! owned by the entries or the [`Graph`](crate::graph::Graph) holding them.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{convert::TryFrom, fmt, sync::Arc};
+
+/// The id of a vertex or an edge.
+///
+/// Numbers are ordered before strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Id {
+    Number(u64),
+    String(IdStr),
+}
+
+/// A string id, shared by its clones.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IdStr(Arc<str>);
+
+impl IdStr {
+    pub fn new(s: &str) -> Self {
+        IdStr(s.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for IdStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Id::Number(n) => n.fmt(f),
+            Id::String(s) => s.0.fmt(f),
+        }
+    }
+}
+
+impl From<u64> for Id {
+    fn from(n: u64) -> Self {
+        Id::Number(n)
+    }
+}
+
+impl From<&str> for Id {
+    fn from(s: &str) -> Self {
+        Id::String(IdStr::new(s))
+    }
+}
+
+impl From<String> for Id {
+    fn from(s: String) -> Self {
+        Id::String(IdStr(s.into()))
+    }
+}
+
+impl From<lsp::NumberOrString> for Id {
+    fn from(id: lsp::NumberOrString) -> Self {
+        match id {
+            lsp::NumberOrString::Number(n) => Id::Number(n),
+            lsp::NumberOrString::String(s) => s.into(),
+        }
+    }
+}
+
+impl From<Id> for lsp::NumberOrString {
+    fn from(id: Id) -> Self {
+        match id {
+            Id::Number(n) => lsp::NumberOrString::Number(n),
+            Id::String(s) => lsp::NumberOrString::String(s.0.to_string()),
+        }
+    }
+}
+
+impl Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Id::Number(n) => serializer.serialize_u64(*n),
+            Id::String(s) => serializer.serialize_str(&s.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = Id;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a non-negative integer or a string")
+            }
+
+            fn visit_u64<E: de::Error>(self, n: u64) -> Result<Id, E> {
+                Ok(Id::Number(n))
+            }
+
+            fn visit_i64<E: de::Error>(self, n: i64) -> Result<Id, E> {
+                match u64::try_from(n) {
+                    Ok(n) => Ok(Id::Number(n)),
+                    Err(_) => Err(E::invalid_value(de::Unexpected::Signed(n), &self)),
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Id, E> {
+                Ok(s.into())
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
@@ -1,11 +1,23 @@
 extern crate lsp_types as lsp;
 
+use serde::{Deserialize, Serialize};
+
+pub mod emitter;
+pub mod graph;
+mod id;
+pub mod query;
+pub mod version;
+
 pub use lsp::Url;
 pub use lsp::{NumberOrString, Range, Position};
+pub use id::{Id, IdStr};
 
-pub type RangeId = lsp::NumberOrString;
+pub type RangeId = Id;
 
+/// The targets of the `result` arrays of results before LSIF 0.5,
+/// which are `item` edges since then.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
 pub enum LocationOrRangeId {
     Location(lsp::Location),
     RangeId(RangeId),
@@ -17,102 +29,404 @@ macro_rules! result_of {
     }
 }
 
+/// A line of a dump.
+///
+/// The wire format is the one of the specification, with the `type`
+/// and `label` of the element next to its id and properties:
+///
+/// ```json
+/// { "id": 4, "type": "edge", "label": "next", "outV": 2, "inV": 3 }
+/// ```
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Entry {
-    pub id: lsp::NumberOrString,
-    data: Element,
+    pub id: Id,
+    #[serde(flatten)]
+    pub data: Element,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum Element {
     Vertex(Vertex),
     Edge(Edge),
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "label", rename_all = "camelCase")]
 pub enum Vertex {
+    /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#the-lsif-graph
+    MetaData(MetaData),
+    /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#events
+    #[serde(rename = "$event")]
+    Event(Event),
+    Source(Source),
+    Capabilities(Capabilities),
     /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#the-project-vertex
     Project(Project),
     Document(Document),
     /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#ranges
-    Range(lsp::Range),
+    Range {
+        #[serde(flatten)]
+        range: lsp::Range,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tag: Option<RangeTag>,
+    },
     /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#result-set
     ResultSet(ResultSet),
+    /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#exports-and-imports
+    Moniker(Moniker),
+    PackageInformation(PackageInformation),
 
-    // Method results
-    DefinitionResult { result: DefinitionResultType },
-    // TODO: Fix ones below to use the { result: LSIFType } format
-    HoverResult(result_of!("textDocument/hover")),
-    ReferenceResult(result_of!("textDocument/references")),
-    // Blocked on https://github.com/gluon-lang/languageserver-types/pull/86
-    // ImplementationResult(result_of!("textDocument/implementation")),
-    // Blocked on https://github.com/gluon-lang/languageserver-types/pull/86
-    // TypeDefinitionResult(result_of!("textDocument/typeDefinition")),
-    FoldingRangeResult(result_of!("textDocument/foldingRange")),
-    DocumentLinkResult(result_of!("textDocument/documentLink")),
-    DocumentSymbolResult(result_of!("textDocument/documentSymbol")),
-    // TODO (these below and more)
-    DiagnosticResult,
+    // Method results, the ranges of the first ones are `item` edges
+    DefinitionResult,
+    DeclarationResult,
+    ReferenceResult,
+    ImplementationResult,
+    TypeDefinitionResult,
+    HoverResult { result: result_of!("textDocument/hover") },
+    FoldingRangeResult { result: result_of!("textDocument/foldingRange") },
+    DocumentLinkResult { result: result_of!("textDocument/documentLink") },
+    DocumentSymbolResult { result: result_of!("textDocument/documentSymbol") },
+    DiagnosticResult { result: Vec<lsp::Diagnostic> },
+    /// The exports and imports of a project before LSIF 0.4,
+    /// which has monikers instead.
     ExportResult,
     ExternalImportResult,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "label", rename_all = "camelCase")]
 pub enum Edge {
-    Contains(EdgeData),
-    RefersTo(EdgeData),
+    Contains(MultiEdgeData),
     Item(Item),
+    Next(EdgeData),
+    /// `next` before LSIF 0.4.
+    RefersTo(EdgeData),
+    /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#exports-and-imports
+    Moniker(EdgeData),
+    NextMoniker(EdgeData),
+    PackageInformation(EdgeData),
+    Attach(EdgeData),
 
     // Methods
+    #[serde(rename = "textDocument/definition")]
     Definition(EdgeData),
+    #[serde(rename = "textDocument/declaration")]
     Declaration(EdgeData),
+    #[serde(rename = "textDocument/hover")]
     Hover(EdgeData),
+    #[serde(rename = "textDocument/references")]
     References(EdgeData),
+    #[serde(rename = "textDocument/implementation")]
     Implementation(EdgeData),
+    #[serde(rename = "textDocument/typeDefinition")]
     TypeDefinition(EdgeData),
+    #[serde(rename = "textDocument/foldingRange")]
     FoldingRange(EdgeData),
+    #[serde(rename = "textDocument/documentLink")]
     DocumentLink(EdgeData),
+    #[serde(rename = "textDocument/documentSymbol")]
     DocumentSymbol(EdgeData),
+    #[serde(rename = "textDocument/diagnostic")]
     Diagnostic(EdgeData),
 }
 
+impl Edge {
+    /// The label of the edge in the dump, e.g. `textDocument/hover`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Edge::Contains(_) => "contains",
+            Edge::Item(_) => "item",
+            Edge::Next(_) => "next",
+            Edge::RefersTo(_) => "refersTo",
+            Edge::Moniker(_) => "moniker",
+            Edge::NextMoniker(_) => "nextMoniker",
+            Edge::PackageInformation(_) => "packageInformation",
+            Edge::Attach(_) => "attach",
+            Edge::Definition(_) => "textDocument/definition",
+            Edge::Declaration(_) => "textDocument/declaration",
+            Edge::Hover(_) => "textDocument/hover",
+            Edge::References(_) => "textDocument/references",
+            Edge::Implementation(_) => "textDocument/implementation",
+            Edge::TypeDefinition(_) => "textDocument/typeDefinition",
+            Edge::FoldingRange(_) => "textDocument/foldingRange",
+            Edge::DocumentLink(_) => "textDocument/documentLink",
+            Edge::DocumentSymbol(_) => "textDocument/documentSymbol",
+            Edge::Diagnostic(_) => "textDocument/diagnostic",
+        }
+    }
+
+    pub fn out_v(&self) -> &Id {
+        match self {
+            Edge::Contains(data) => &data.out_v,
+            Edge::Item(item) => &item.edge_data.out_v,
+            Edge::Next(data)
+            | Edge::RefersTo(data)
+            | Edge::Moniker(data)
+            | Edge::NextMoniker(data)
+            | Edge::PackageInformation(data)
+            | Edge::Attach(data)
+            | Edge::Definition(data)
+            | Edge::Declaration(data)
+            | Edge::Hover(data)
+            | Edge::References(data)
+            | Edge::Implementation(data)
+            | Edge::TypeDefinition(data)
+            | Edge::FoldingRange(data)
+            | Edge::DocumentLink(data)
+            | Edge::DocumentSymbol(data)
+            | Edge::Diagnostic(data) => &data.out_v,
+        }
+    }
+
+    /// The in vertices, one for the edges with an `inV`.
+    pub fn in_vs(&self) -> &[Id] {
+        match self {
+            Edge::Contains(data) => &data.in_vs,
+            Edge::Item(item) => &item.edge_data.in_vs,
+            Edge::Next(data)
+            | Edge::RefersTo(data)
+            | Edge::Moniker(data)
+            | Edge::NextMoniker(data)
+            | Edge::PackageInformation(data)
+            | Edge::Attach(data)
+            | Edge::Definition(data)
+            | Edge::Declaration(data)
+            | Edge::Hover(data)
+            | Edge::References(data)
+            | Edge::Implementation(data)
+            | Edge::TypeDefinition(data)
+            | Edge::FoldingRange(data)
+            | Edge::DocumentLink(data)
+            | Edge::DocumentSymbol(data)
+            | Edge::Diagnostic(data) => std::slice::from_ref(&data.in_v),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EdgeData {
-    in_v: lsp::NumberOrString,
-    out_v: lsp::NumberOrString,
+    pub in_v: Id,
+    pub out_v: Id,
 }
 
+/// The data of edges with several in vertices.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub enum DefinitionResultType {
-    Scalar(LocationOrRangeId),
-    Array(LocationOrRangeId),
+#[serde(rename_all = "camelCase")]
+pub struct MultiEdgeData {
+    pub in_vs: Vec<Id>,
+    pub out_v: Id,
 }
 
+/// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#request-textdocumentreferences
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub enum Item {
-    Definition(EdgeData),
-    Reference(EdgeData),
+pub struct Item {
+    /// The document the in vertices belong to,
+    /// `document` before LSIF 0.6.
+    #[serde(alias = "document")]
+    pub shard: Id,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<ItemKind>,
+    #[serde(flatten)]
+    pub edge_data: MultiEdgeData,
+}
+
+/// The role of the in vertices of an `item` edge of a result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ItemKind {
+    Definitions,
+    References,
+    /// Reference results of other projects.
+    ReferenceResults,
+    /// Monikers, whose references are in other dumps.
+    ReferenceLinks,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaData {
+    /// The version of the LSIF format, e.g. `0.6.0`.
+    pub version: String,
+    /// Always `utf-16` as of LSIF 0.6.
+    pub position_encoding: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_root: Option<lsp::Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_info: Option<ToolInfo>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<String>>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub kind: EventKind,
+    pub scope: EventScope,
+    /// The project or document that begins or ends.
+    pub data: Id,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Begin,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventScope {
+    Project,
+    Document,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    pub workspace_root: lsp::Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<Repository>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Repository {
+    /// The version control system, e.g. `git`.
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_id: Option<String>,
+}
+
+/// The requests the dump has results for.
+///
+/// Missing providers are `false`.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Capabilities {
+    pub hover_provider: bool,
+    pub declaration_provider: bool,
+    pub definition_provider: bool,
+    pub type_definition_provider: bool,
+    pub references_provider: bool,
+    pub document_symbol_provider: bool,
+    pub folding_range_provider: bool,
+    pub diagnostic_provider: bool,
 }
 
+/// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#the-project-vertex
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    /// The language of the project, e.g. `typescript`.
+    pub kind: Language,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The project file, e.g. a `tsconfig.json`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<lsp::Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Document {
-    uri: lsp::Url,
-    language_id: Language,
+    pub uri: lsp::Url,
+    pub language_id: Language,
+    /// The content of the document, encoded in base64.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>,
+}
+
+/// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#document-symbols
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RangeTag {
+    Declaration(DeclarationTag),
+    Definition(DeclarationTag),
+    Reference { text: String },
+    Unknown { text: String },
+}
+
+/// The tag of declaration and definition ranges.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclarationTag {
+    pub text: String,
+    pub kind: lsp::SymbolKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<bool>,
+    pub full_range: lsp::Range,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
 }
 
 /// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#result-set
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ResultSet {
-    key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
 }
 
-/// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#the-project-vertex
+/// https://github.com/Microsoft/language-server-protocol/blob/master/indexFormat/specification.md#exports-and-imports
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub struct Project {
-    project_file: lsp::Url,
-    language_id: Language,
+pub struct Moniker {
+    /// The indexer or package manager the identifier is unique in, e.g. `tsc` or `npm`.
+    pub scheme: String,
+    pub identifier: String,
+    pub unique: UniquenessLevel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<MonikerKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UniquenessLevel {
+    Document,
+    Project,
+    Group,
+    Scheme,
+    Global,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MonikerKind {
+    Import,
+    Export,
+    Local,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageInformation {
+    pub name: String,
+    /// The package manager, e.g. `npm` or `cargo`.
+    pub manager: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<lsp::Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<Repository>,
 }
 
 /// https://github.com/Microsoft/language-server-protocol/issues/213
 /// For examples, see: https://code.visualstudio.com/docs/languages/identifiers.
 pub type Language = String;
+
+#[cfg(test)]
+mod tests;
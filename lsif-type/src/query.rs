@@ -0,0 +1,180 @@
+//! Answering requests from the graph of a dump.
+//!
+//! A request at a position of a document is answered from the innermost
+//! range that contains the position. The result of the request is the one
+//! attached to the range, or to the first result set of its `next` chain
+//! that has one:
+//!
+//! ```text
+//! range -next-> resultSet -next-> resultSet -textDocument/definition-> definitionResult
+//! ```
+
+use crate::{graph::Graph, Document, Id, ItemKind, Vertex};
+use lsp::request::{
+    GotoDeclarationResponse, GotoImplementationResponse, GotoTypeDefinitionResponse,
+};
+use std::collections::HashSet;
+
+impl Graph {
+    /// The innermost range of the document that contains the position,
+    /// its ends included.
+    pub fn range_at(&self, uri: &lsp::Url, position: lsp::Position) -> Option<&Id> {
+        let document = self.document(uri)?;
+        self.out_vertices(document, "contains")
+            .filter_map(|id| match self.vertex(id) {
+                Some(Vertex::Range { range, .. })
+                    if range.start <= position && position <= range.end =>
+                {
+                    Some((id, range))
+                }
+                _ => None,
+            })
+            .max_by(|(_, a), (_, b)| a.start.cmp(&b.start).then(b.end.cmp(&a.end)))
+            .map(|(id, _)| id)
+    }
+
+    /// The result of a request for a range, or a result set, from the edge
+    /// with the label of the request, following the `next` chain.
+    pub fn result(&self, vertex: &Id, label: &'static str) -> Option<&Id> {
+        let mut vertex = vertex;
+        // in case the chain has a cycle
+        let mut visited = HashSet::new();
+        while visited.insert(vertex) {
+            if let Some(result) = self.out_vertices(vertex, label).next() {
+                return Some(result);
+            }
+            vertex = self.next(vertex)?;
+        }
+        None
+    }
+
+    /// The locations of the ranges of the `item` edges of a result.
+    pub fn item_locations(&self, result: &Id) -> Vec<lsp::Location> {
+        self.all_items(result)
+            .filter_map(|item| {
+                let uri = self.uri(&item.shard)?;
+                Some(self.locations(uri, &item.edge_data.in_vs))
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// The definitions of the symbol at the position.
+    pub fn definition(&self, uri: &lsp::Url, position: lsp::Position) -> Vec<lsp::Location> {
+        self.range_at(uri, position)
+            .and_then(|range| self.result(range, "textDocument/definition"))
+            .map(|result| self.item_locations(result))
+            .unwrap_or_default()
+    }
+
+    /// The hover of the symbol at the position, with the range
+    /// of the symbol if the hover has none.
+    pub fn hover(&self, uri: &lsp::Url, position: lsp::Position) -> Option<lsp::Hover> {
+        let range = self.range_at(uri, position)?;
+        let result = self.result(range, "textDocument/hover")?;
+        let mut hover = match self.vertex(result)? {
+            Vertex::HoverResult { result } => result.clone()?,
+            _ => return None,
+        };
+        if hover.range.is_none() {
+            if let Some(Vertex::Range { range, .. }) = self.vertex(range) {
+                hover.range = Some(*range);
+            }
+        }
+        Some(hover)
+    }
+
+    /// The references of the symbol at the position, and its definitions
+    /// if `include_declaration` is set.
+    ///
+    /// The reference results of other projects, linked by `item` edges with
+    /// the `referenceResults` property, are included. Monikers of references
+    /// in other dumps, the `referenceLinks`, are not.
+    pub fn references(
+        &self,
+        uri: &lsp::Url,
+        position: lsp::Position,
+        include_declaration: bool,
+    ) -> Option<Vec<lsp::Location>> {
+        let range = self.range_at(uri, position)?;
+        let mut pending = vec![self.result(range, "textDocument/references")?];
+        let mut visited = HashSet::new();
+        let mut locations = Vec::new();
+        while let Some(result) = pending.pop() {
+            if !visited.insert(result) {
+                continue;
+            }
+            for item in self.all_items(result) {
+                let ranges = &item.edge_data.in_vs;
+                match item.property {
+                    Some(ItemKind::ReferenceResults) => pending.extend(ranges.iter().rev()),
+                    Some(ItemKind::ReferenceLinks) => {}
+                    Some(ItemKind::Definitions) if !include_declaration => {}
+                    Some(ItemKind::Definitions) | Some(ItemKind::References) | None => {
+                        if let Some(uri) = self.uri(&item.shard) {
+                            locations.extend(self.locations(uri, ranges));
+                        }
+                    }
+                }
+            }
+        }
+        Some(locations)
+    }
+
+    /// The implementations of the symbol at the position.
+    pub fn implementation(
+        &self,
+        uri: &lsp::Url,
+        position: lsp::Position,
+    ) -> Option<GotoImplementationResponse> {
+        self.goto(uri, position, "textDocument/implementation")
+    }
+
+    /// The definitions of the type of the symbol at the position.
+    pub fn type_definition(
+        &self,
+        uri: &lsp::Url,
+        position: lsp::Position,
+    ) -> Option<GotoTypeDefinitionResponse> {
+        self.goto(uri, position, "textDocument/typeDefinition")
+    }
+
+    /// The declarations of the symbol at the position.
+    pub fn declaration(
+        &self,
+        uri: &lsp::Url,
+        position: lsp::Position,
+    ) -> Option<GotoDeclarationResponse> {
+        self.goto(uri, position, "textDocument/declaration")
+    }
+
+    /// The locations of the result of a request, `None` if there is no result.
+    fn goto(
+        &self,
+        uri: &lsp::Url,
+        position: lsp::Position,
+        label: &'static str,
+    ) -> Option<lsp::GotoDefinitionResponse> {
+        let range = self.range_at(uri, position)?;
+        let result = self.result(range, label)?;
+        Some(self.item_locations(result).into())
+    }
+
+    fn uri(&self, document: &Id) -> Option<&lsp::Url> {
+        match self.vertex(document) {
+            Some(Vertex::Document(Document { uri, .. })) => Some(uri),
+            _ => None,
+        }
+    }
+
+    fn locations<'a>(
+        &'a self,
+        uri: &'a lsp::Url,
+        ranges: &'a [Id],
+    ) -> impl Iterator<Item = lsp::Location> + 'a {
+        ranges.iter().filter_map(move |id| match self.vertex(id) {
+            Some(Vertex::Range { range, .. }) => Some(lsp::Location::new(uri.clone(), *range)),
+            _ => None,
+        })
+    }
+}
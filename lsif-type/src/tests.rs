@@ -0,0 +1,582 @@
+use crate::{
+    emitter::{Emitter, Error as EmitterError},
+    graph::Graph,
+    version::{self, Version},
+    Capabilities, Document, Edge, EdgeData, Element, Entry, EventScope, Id, Item, ItemKind,
+    LocationOrRangeId, MetaData, MultiEdgeData, Position, Project, Range, ResultSet, Url, Vertex,
+};
+use serde_json::Value;
+
+/// Every line must deserialize, and serialize back to the same JSON,
+/// except for the properties renamed since the version of the dump.
+fn round_trip(dump: &str, renamed: &[(&str, &str)]) -> Vec<Entry> {
+    dump.lines()
+        .map(|line| {
+            let entry: Entry =
+                serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {}", e, line));
+
+            let mut expected: Value = serde_json::from_str(line).unwrap();
+            let object = expected.as_object_mut().unwrap();
+            for (old, new) in renamed {
+                if let Some(v) = object.remove(*old) {
+                    object.insert(new.to_string(), v);
+                }
+            }
+            assert_eq!(serde_json::to_value(&entry).unwrap(), expected, "{}", line);
+            entry
+        })
+        .collect()
+}
+
+// The fixtures in `tests/` were written by hand in the shape of the output
+// of lsif-node (LSIF 0.6) and lsif-go (LSIF 0.4), they are not captured
+// from these indexers. The golden tests below read captured dumps.
+
+#[test]
+fn test_lsif_0_6() {
+    let entries = round_trip(include_str!("tests/lsif-0.6.lsif"), &[]);
+    assert_eq!(entries.len(), 26);
+
+    match &entries[10].data {
+        Element::Vertex(Vertex::Range { range, tag }) => {
+            assert_eq!(range.start.character, 16);
+            assert!(tag.is_some());
+        }
+        e => panic!("expected a range, got {:?}", e),
+    }
+    match &entries[19].data {
+        Element::Edge(Edge::Item(item)) => {
+            assert_eq!(item.property, Some(ItemKind::Definitions));
+            assert_eq!(item.edge_data.in_vs.len(), 1);
+        }
+        e => panic!("expected an item, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_lsif_0_4() {
+    let entries = round_trip(
+        include_str!("tests/lsif-0.4.lsif"),
+        &[("document", "shard")],
+    );
+    assert_eq!(entries.len(), 16);
+
+    match &entries[14].data {
+        Element::Edge(Edge::Item(item)) => assert_eq!(item.shard, Id::from(4)),
+        e => panic!("expected an item, got {:?}", e),
+    }
+}
+
+/// Dumps captured from the indexers, kept in `samples/` with the other
+/// real dumps, run with `cargo test -- --ignored`.
+fn golden(name: &str) -> String {
+    let path = format!("../samples/{}", name);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+}
+
+#[test]
+#[ignore = "needs samples/lsif-node.lsif, a dump captured from lsif-node"]
+fn test_golden_lsif_node() {
+    round_trip(&golden("lsif-node.lsif"), &[]);
+}
+
+#[test]
+#[ignore = "needs samples/lsif-go.lsif, a dump captured from lsif-go"]
+fn test_golden_lsif_go() {
+    round_trip(&golden("lsif-go.lsif"), &[("document", "shard")]);
+}
+
+#[test]
+fn test_serialize() {
+    let entry = Entry {
+        id: Id::from(4),
+        data: Element::Edge(Edge::Next(EdgeData {
+            in_v: Id::from(3),
+            out_v: Id::from(2),
+        })),
+    };
+    assert_eq!(
+        serde_json::to_string(&entry).unwrap(),
+        r#"{"id":4,"type":"edge","label":"next","inV":3,"outV":2}"#
+    );
+
+    // labels of older versions
+    round_trip(
+        "{\"id\":1,\"type\":\"vertex\",\"label\":\"exportResult\"}\n\
+         {\"id\":2,\"type\":\"vertex\",\"label\":\"externalImportResult\"}\n\
+         {\"id\":3,\"type\":\"edge\",\"label\":\"refersTo\",\"inV\":5,\"outV\":4}",
+        &[],
+    );
+
+    // the targets of `result` arrays are bare ids or locations.
+    let targets: Vec<LocationOrRangeId> =
+        serde_json::from_str(r#"[5,{"uri":"file:///a.ts","range":{"start":{"line":0,"character":1},"end":{"line":0,"character":2}}}]"#).unwrap();
+    assert_eq!(targets[0], LocationOrRangeId::RangeId(Id::from(5)));
+    assert!(matches!(targets[1], LocationOrRangeId::Location(_)));
+    assert_eq!(serde_json::to_string(&targets[0]).unwrap(), "5");
+
+    let entry: Entry = serde_json::from_str(
+        r#"{"id":3,"type":"vertex","label":"capabilities","hoverProvider":true}"#,
+    )
+    .unwrap();
+    match entry.data {
+        Element::Vertex(Vertex::Capabilities(capabilities)) => assert_eq!(
+            capabilities,
+            Capabilities {
+                hover_provider: true,
+                ..Capabilities::default()
+            }
+        ),
+        e => panic!("expected capabilities, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_id() {
+    let a = Id::from("a");
+    assert_eq!(a, Id::from(String::from("a")));
+    assert_ne!(a, Id::from("b"));
+    assert!(Id::from(9) < Id::from(10));
+    assert!(Id::from(10) < a);
+    assert!(Id::from("a") < Id::from("b"));
+
+    let ids: Vec<Id> = serde_json::from_str(r#"[1, "a", "1"]"#).unwrap();
+    assert_eq!(ids, vec![Id::from(1), a.clone(), Id::from("1")]);
+    assert_eq!(serde_json::to_string(&ids).unwrap(), r#"[1,"a","1"]"#);
+    assert!(serde_json::from_str::<Id>("-1").is_err());
+
+    let lsp = lsp::NumberOrString::String("a".into());
+    assert_eq!(Id::from(lsp.clone()), a);
+    assert_eq!(lsp::NumberOrString::from(a), lsp);
+}
+
+#[test]
+fn test_version() {
+    assert_eq!("0.4.3".parse::<Version>().unwrap(), Version::V0_4);
+    assert_eq!("0.5.0-next.1".parse::<Version>().unwrap(), Version::V0_5);
+    assert_eq!("0.6.0".parse::<Version>().unwrap(), Version::CURRENT);
+    assert!("1.0.0".parse::<Version>().is_err());
+
+    let entries = version::upgrade(include_str!("tests/lsif-0.4.lsif")).unwrap();
+    assert_eq!(entries.len(), 16);
+    match &entries[0].data {
+        Element::Vertex(Vertex::MetaData(m)) => assert_eq!(m.version, "0.6.0"),
+        e => panic!("expected metadata, got {:?}", e),
+    }
+
+    let line = r#"{"id":15,"type":"edge","label":"item","outV":13,"inVs":[5],"document":4}"#;
+    assert!(serde_json::from_str::<Entry>(line).is_ok());
+    assert!(version::from_str(line, Version::V0_5).is_ok());
+
+    let line = r#"{"id":16,"type":"edge","label":"refersTo","outV":5,"inV":6}"#;
+    match version::from_str(line, Version::V0_4).unwrap().data {
+        Element::Edge(Edge::Next(data)) => assert_eq!(data.in_v, Id::from(6)),
+        e => panic!("expected a next edge, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_upgrade_items_without_document() {
+    let dump = r#"{"id":1,"type":"vertex","label":"metaData","version":"0.4.0","positionEncoding":"utf-16"}
+{"id":2,"type":"vertex","label":"document","uri":"file:///a.ts","languageId":"typescript"}
+{"id":3,"type":"vertex","label":"range","start":{"line":0,"character":0},"end":{"line":0,"character":1}}
+{"id":4,"type":"vertex","label":"definitionResult"}
+{"id":5,"type":"edge","label":"item","outV":4,"inVs":[3]}
+{"id":6,"type":"edge","label":"contains","outV":2,"inVs":[3]}
+{"id":7,"type":"edge","label":"item","outV":4,"inVs":[3]}"#;
+
+    let line = dump.lines().nth(4).unwrap();
+    assert!(version::from_str(line, Version::V0_4).is_err());
+
+    let entries = version::upgrade(dump).unwrap();
+    let ids = entries
+        .iter()
+        .map(|e| match &e.id {
+            Id::Number(n) => *n,
+            id => panic!("expected a number, got {:?}", id),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![1, 2, 3, 4, 6, 5, 7]);
+    for entry in &entries[5..] {
+        match &entry.data {
+            Element::Edge(Edge::Item(item)) => assert_eq!(item.shard, Id::from(2)),
+            e => panic!("expected an item, got {:?}", e),
+        }
+    }
+
+    let unfinished = dump.lines().take(5).collect::<Vec<_>>().join("\n");
+    assert!(matches!(
+        version::upgrade(&unfinished),
+        Err(version::Error::MissingShard { .. })
+    ));
+}
+
+fn emitter_with_document() -> (Emitter<Vec<u8>>, Id) {
+    let mut emitter = Emitter::new(Vec::new());
+    emitter
+        .emit_vertex(Vertex::MetaData(MetaData {
+            version: Version::CURRENT.to_string(),
+            position_encoding: "utf-16".into(),
+            project_root: None,
+            tool_info: None,
+        }))
+        .unwrap();
+    let document = emitter
+        .emit_vertex(Vertex::Document(Document {
+            uri: Url::parse("file:///a.rs").unwrap(),
+            language_id: "rust".into(),
+            contents: None,
+        }))
+        .unwrap();
+    (emitter, document)
+}
+
+#[test]
+fn test_emitter() {
+    let (mut emitter, document) = emitter_with_document();
+    emitter.begin(EventScope::Document, document.clone()).unwrap();
+    let hover = lsp::Hover {
+        contents: lsp::HoverContents::Scalar(lsp::MarkedString::String("fn main()".into())),
+        range: None,
+    };
+    let range = Range::new(Position::new(0, 3), Position::new(0, 7));
+    let definition = emitter
+        .emit_range_with_definition(document.clone(), range, Some(hover))
+        .unwrap();
+    emitter.end(EventScope::Document, document.clone()).unwrap();
+
+    let dump = String::from_utf8(emitter.finish().unwrap()).unwrap();
+    let entries = round_trip(&dump, &[]);
+    assert_eq!(entries.len(), 13);
+    assert!(entries.iter().zip(1..).all(|(e, i)| e.id == Id::from(i)));
+    assert_eq!(definition.range, Id::from(4));
+    match &entries[9].data {
+        Element::Edge(Edge::Item(item)) => {
+            assert_eq!(item.shard, document);
+            assert_eq!(item.edge_data.out_v, definition.definition_result);
+            assert_eq!(item.edge_data.in_vs, vec![definition.range]);
+        }
+        e => panic!("expected an item, got {:?}", e),
+    }
+    assert_eq!(definition.hover_result, Some(Id::from(11)));
+}
+
+#[test]
+fn test_emitter_errors() {
+    let mut emitter = Emitter::new(Vec::new());
+    assert!(matches!(
+        emitter.emit_vertex(Vertex::DefinitionResult),
+        Err(EmitterError::MissingMetaData)
+    ));
+
+    let (mut emitter, document) = emitter_with_document();
+    let unknown = Id::from("unknown");
+    assert!(matches!(
+        emitter.emit_edge(Edge::Next(EdgeData {
+            in_v: unknown.clone(),
+            out_v: document.clone(),
+        })),
+        Err(EmitterError::UnknownVertex { id }) if id == unknown
+    ));
+
+    let project = emitter
+        .emit_vertex(Vertex::Project(Project {
+            kind: "rust".into(),
+            name: None,
+            resource: None,
+            contents: None,
+        }))
+        .unwrap();
+    emitter.begin(EventScope::Project, project.clone()).unwrap();
+    emitter.begin(EventScope::Document, document.clone()).unwrap();
+    assert!(matches!(
+        emitter.end(EventScope::Project, project.clone()),
+        Err(EmitterError::UnbalancedEvent { .. })
+    ));
+    emitter.end(EventScope::Document, document.clone()).unwrap();
+
+    let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+    assert!(matches!(
+        emitter.emit_range_with_definition(document.clone(), range, None),
+        Err(EmitterError::ElementAfterEnd { data }) if data == document
+    ));
+    assert!(matches!(
+        emitter.finish(),
+        Err(EmitterError::UnendedEvent { data, .. }) if data == project
+    ));
+}
+
+#[test]
+fn test_graph() {
+    let graph: Graph = round_trip(include_str!("tests/lsif-0.6.lsif"), &[])
+        .into_iter()
+        .collect();
+    let uri = Url::parse("file:///home/user/sample/src/index.ts").unwrap();
+    let document = graph.document(&uri).unwrap();
+    assert_eq!(document, &Id::from(6));
+
+    let ranges = graph.out_vertices(document, "contains").collect::<Vec<_>>();
+    assert_eq!(ranges, vec![&Id::from(11)]);
+    assert!(matches!(
+        graph.vertex(ranges[0]),
+        Some(Vertex::Range { .. })
+    ));
+    assert_eq!(
+        graph.in_vertices(ranges[0], "contains").next(),
+        Some(document)
+    );
+
+    let result_set = graph.next(ranges[0]).unwrap();
+    assert_eq!(result_set, &Id::from(8));
+    assert_eq!(graph.next(result_set), None);
+    let hover = graph.out_edges(result_set, "textDocument/hover").next();
+    assert_eq!(hover.map(Edge::in_vs), Some(&[Id::from(16)][..]));
+
+    let references = graph
+        .out_vertices(result_set, "textDocument/references")
+        .next();
+    let items = graph
+        .items(references.unwrap(), document)
+        .collect::<Vec<_>>();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].property, Some(ItemKind::Definitions));
+    assert_eq!(graph.items(references.unwrap(), &Id::from(4)).count(), 0);
+    assert_eq!(graph.all_items(&Id::from(13)).count(), 1);
+}
+
+/// A dump of two documents, `a.rs` defining `foo` in `mod a { fn foo() {} }`,
+/// and `b.rs` calling it, with the result set of `foo` behind a second one.
+fn two_documents() -> Graph {
+    let (mut emitter, a) = emitter_with_document();
+    let b = emitter
+        .emit_vertex(Vertex::Document(Document {
+            uri: Url::parse("file:///b.rs").unwrap(),
+            language_id: "rust".into(),
+            contents: None,
+        }))
+        .unwrap();
+
+    emitter.begin(EventScope::Document, a.clone()).unwrap();
+    let module = Range::new(Position::new(0, 0), Position::new(0, 22));
+    emitter
+        .emit_range_with_definition(a.clone(), module, None)
+        .unwrap();
+    let foo = Range::new(Position::new(0, 11), Position::new(0, 14));
+    let definition = emitter
+        .emit_range_with_definition(a.clone(), foo, None)
+        .unwrap();
+    emitter.end(EventScope::Document, a).unwrap();
+
+    emitter.begin(EventScope::Document, b.clone()).unwrap();
+    let call = Range::new(Position::new(1, 7), Position::new(1, 10));
+    let call = emitter
+        .emit_vertex(Vertex::Range {
+            range: call,
+            tag: None,
+        })
+        .unwrap();
+    emitter
+        .emit_edge(Edge::Contains(MultiEdgeData {
+            in_vs: vec![call.clone()],
+            out_v: b.clone(),
+        }))
+        .unwrap();
+    let result_set = emitter
+        .emit_vertex(Vertex::ResultSet(ResultSet { key: None }))
+        .unwrap();
+    emitter
+        .emit_edge(Edge::Next(EdgeData {
+            in_v: result_set.clone(),
+            out_v: call,
+        }))
+        .unwrap();
+    emitter
+        .emit_edge(Edge::Next(EdgeData {
+            in_v: definition.result_set,
+            out_v: result_set,
+        }))
+        .unwrap();
+    emitter.end(EventScope::Document, b).unwrap();
+
+    let dump = String::from_utf8(emitter.finish().unwrap()).unwrap();
+    round_trip(&dump, &[]).into_iter().collect()
+}
+
+#[test]
+fn test_definition() {
+    let graph = two_documents();
+    let a = Url::parse("file:///a.rs").unwrap();
+    let b = Url::parse("file:///b.rs").unwrap();
+    let foo = lsp::Location::new(
+        a.clone(),
+        Range::new(Position::new(0, 11), Position::new(0, 14)),
+    );
+
+    // the innermost range, ends included
+    for character in &[11, 12, 14] {
+        assert_eq!(
+            graph.definition(&a, Position::new(0, *character)),
+            vec![foo.clone()]
+        );
+    }
+    assert_eq!(
+        graph.definition(&a, Position::new(0, 2))[0].range.end,
+        Position::new(0, 22)
+    );
+    assert_eq!(graph.definition(&b, Position::new(1, 8)), vec![foo]);
+
+    assert_eq!(graph.definition(&b, Position::new(1, 11)), vec![]);
+    assert_eq!(
+        graph.definition(&Url::parse("file:///c.rs").unwrap(), Position::new(0, 0)),
+        vec![]
+    );
+}
+
+#[test]
+fn test_queries() {
+    let (mut emitter, a) = emitter_with_document();
+    let edge = |emitter: &mut Emitter<Vec<u8>>, edge| emitter.emit_edge(edge).unwrap();
+    let item = |out_v, in_vs, shard, property| {
+        Edge::Item(Item {
+            shard,
+            property,
+            edge_data: MultiEdgeData { in_vs, out_v },
+        })
+    };
+
+    emitter.begin(EventScope::Document, a.clone()).unwrap();
+    let hover = lsp::Hover {
+        contents: lsp::HoverContents::Scalar(lsp::MarkedString::String("fn foo()".into())),
+        range: None,
+    };
+    let foo = Range::new(Position::new(0, 3), Position::new(0, 6));
+    let definition = emitter
+        .emit_range_with_definition(a.clone(), foo, Some(hover))
+        .unwrap();
+    let call = Range::new(Position::new(4, 0), Position::new(4, 3));
+    let call = emitter
+        .emit_vertex(Vertex::Range {
+            range: call,
+            tag: None,
+        })
+        .unwrap();
+    let other = Range::new(Position::new(8, 0), Position::new(8, 3));
+    let other = emitter
+        .emit_vertex(Vertex::Range {
+            range: other,
+            tag: None,
+        })
+        .unwrap();
+    edge(
+        &mut emitter,
+        Edge::Contains(MultiEdgeData {
+            in_vs: vec![call.clone(), other.clone()],
+            out_v: a.clone(),
+        }),
+    );
+    edge(
+        &mut emitter,
+        Edge::Next(EdgeData {
+            in_v: definition.result_set.clone(),
+            out_v: call.clone(),
+        }),
+    );
+
+    // the references of another project, at `other`
+    let project = emitter.emit_vertex(Vertex::ReferenceResult).unwrap();
+    edge(
+        &mut emitter,
+        item(
+            project.clone(),
+            vec![other.clone()],
+            a.clone(),
+            Some(ItemKind::References),
+        ),
+    );
+    let references = emitter.emit_vertex(Vertex::ReferenceResult).unwrap();
+    let result_set = definition.result_set;
+    edge(
+        &mut emitter,
+        Edge::References(EdgeData {
+            in_v: references.clone(),
+            out_v: result_set.clone(),
+        }),
+    );
+    edge(
+        &mut emitter,
+        item(
+            references.clone(),
+            vec![definition.range],
+            a.clone(),
+            Some(ItemKind::Definitions),
+        ),
+    );
+    edge(
+        &mut emitter,
+        item(
+            references.clone(),
+            vec![call],
+            a.clone(),
+            Some(ItemKind::References),
+        ),
+    );
+    edge(
+        &mut emitter,
+        item(
+            references,
+            vec![project],
+            a.clone(),
+            Some(ItemKind::ReferenceResults),
+        ),
+    );
+
+    let implementation = emitter.emit_vertex(Vertex::ImplementationResult).unwrap();
+    edge(
+        &mut emitter,
+        Edge::Implementation(EdgeData {
+            in_v: implementation.clone(),
+            out_v: result_set,
+        }),
+    );
+    edge(&mut emitter, item(implementation, vec![other], a.clone(), None));
+    emitter.end(EventScope::Document, a).unwrap();
+
+    let dump = String::from_utf8(emitter.finish().unwrap()).unwrap();
+    let graph: Graph = round_trip(&dump, &[]).into_iter().collect();
+    let uri = Url::parse("file:///a.rs").unwrap();
+    let location = |range: Range| lsp::Location::new(uri.clone(), range);
+    let (call, other) = (Position::new(4, 1), Position::new(8, 0));
+
+    let hover = graph.hover(&uri, call).unwrap();
+    assert_eq!(
+        hover.range,
+        Some(Range::new(Position::new(4, 0), Position::new(4, 3)))
+    );
+    assert!(graph.hover(&uri, other).is_none());
+
+    let lines = |locations: Vec<lsp::Location>| {
+        let mut lines = locations
+            .iter()
+            .map(|l| l.range.start.line)
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines
+    };
+    let references = graph.references(&uri, call, true).unwrap();
+    assert_eq!(references[0], location(foo));
+    assert_eq!(lines(references), vec![0, 4, 8]);
+    assert_eq!(
+        lines(graph.references(&uri, call, false).unwrap()),
+        vec![4, 8]
+    );
+    assert_eq!(graph.references(&uri, other, true), None);
+
+    let expected =
+        lsp::GotoDefinitionResponse::Array(vec![location(Range::new(other, Position::new(8, 3)))]);
+    assert_eq!(
+        graph.implementation(&uri, Position::new(0, 4)),
+        Some(expected)
+    );
+    assert_eq!(graph.type_definition(&uri, call), None);
+    assert_eq!(graph.declaration(&uri, call), None);
+}
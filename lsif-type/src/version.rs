@@ -0,0 +1,259 @@
+//! Dumps of older versions of the specification.
+//!
+//! The version of a dump is the one of its `metaData` vertex, which is
+//! the first line. Lines of older versions are rewritten into the current
+//! model before they are deserialized:
+//!
+//! - 0.5 names the `shard` of `item` edges `document`.
+//! - 0.4 also emits `item` edges without a document. Their document is
+//!   the one that contains their ranges, so they are held back until the
+//!   `contains` edge of their first range is read.
+//! - `next` edges may still have their earlier `refersTo` label.
+//!
+//! The `version` of the upgraded `metaData` is the current one.
+
+use crate::{Element, Entry, Id, Vertex};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// The versions of the specification that can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Version {
+    V0_4,
+    V0_5,
+    V0_6,
+}
+
+impl Version {
+    pub const CURRENT: Version = Version::V0_6;
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Version::V0_4 => "0.4.0",
+            Version::V0_5 => "0.5.0",
+            Version::V0_6 => "0.6.0",
+        }
+    }
+
+    /// The version of a dump from its first entry,
+    /// `None` if it is not a `metaData` vertex.
+    pub fn detect(entry: &Entry) -> Option<Result<Version, Error>> {
+        match &entry.data {
+            Element::Vertex(Vertex::MetaData(m)) => Some(m.version.parse()),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    /// Any patch version, and prereleases such as `0.4.3-alpha`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(['.', '-']);
+        match (parts.next(), parts.next()) {
+            (Some("0"), Some("4")) => Ok(Version::V0_4),
+            (Some("0"), Some("5")) => Ok(Version::V0_5),
+            (Some("0"), Some("6")) => Ok(Version::V0_6),
+            _ => Err(Error::UnknownVersion(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    UnknownVersion(String),
+    /// An `item` edge without a document, whose ranges
+    /// are not contained in any document.
+    MissingShard {
+        id: Id,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Json(e) => e.fmt(f),
+            Error::UnknownVersion(v) => write!(f, "unknown LSIF version \"{}\"", v),
+            Error::MissingShard { id } => write!(f, "cannot find the document of item {}", id),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+/// Deserialize a line of a dump of the given version.
+///
+/// `item` edges of 0.4 without a document cannot be read on their own,
+/// those need an [`Upgrader`].
+pub fn from_str(line: &str, version: Version) -> Result<Entry, Error> {
+    if version == Version::CURRENT {
+        return Ok(serde_json::from_str(line)?);
+    }
+    let mut value: Map<String, Value> = serde_json::from_str(line)?;
+    rewrite(&mut value);
+    if is_item(&value) && !value.contains_key("shard") {
+        return Err(Error::MissingShard { id: id(&value)? });
+    }
+    Ok(serde_json::from_value(Value::Object(value))?)
+}
+
+/// Rewrite the properties renamed since older versions.
+fn rewrite(value: &mut Map<String, Value>) {
+    if is_item(value) {
+        if let Some(document) = value.remove("document") {
+            value.insert("shard".into(), document);
+        }
+    }
+    if is_edge(value, "refersTo") {
+        value.insert("label".into(), "next".into());
+    }
+    if value.get("label").and_then(Value::as_str) == Some("metaData") {
+        value.insert("version".into(), Version::CURRENT.as_str().into());
+        value
+            .entry("positionEncoding")
+            .or_insert_with(|| "utf-16".into());
+    }
+}
+
+fn is_item(value: &Map<String, Value>) -> bool {
+    is_edge(value, "item")
+}
+
+fn is_edge(value: &Map<String, Value>, label: &str) -> bool {
+    value.get("type").and_then(Value::as_str) == Some("edge")
+        && value.get("label").and_then(Value::as_str) == Some(label)
+}
+
+fn id(value: &Map<String, Value>) -> Result<Id, Error> {
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    Ok(serde_json::from_value(id)?)
+}
+
+/// Reads the lines of a dump of any version into the current model.
+///
+/// Dumps without a `metaData` vertex first are read as current ones.
+#[derive(Debug, Default)]
+pub struct Upgrader {
+    version: Option<Version>,
+    /// The documents of the ranges, from the `contains` edges.
+    documents: HashMap<Id, Value>,
+    /// The `item` edges without a document, by their first range.
+    pending: HashMap<Id, Vec<Map<String, Value>>>,
+}
+
+impl Upgrader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The version of the dump, once its first line is read.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Read a line, and return the entries that are complete,
+    /// which can include `item` edges held back before.
+    pub fn push(&mut self, line: &str) -> Result<Vec<Entry>, Error> {
+        let mut value: Map<String, Value> = serde_json::from_str(line)?;
+
+        let version = match self.version {
+            Some(v) => v,
+            None => {
+                let version = match value.get("label").and_then(Value::as_str) {
+                    Some("metaData") => value
+                        .get("version")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .parse()?,
+                    _ => Version::CURRENT,
+                };
+                self.version = Some(version);
+                version
+            }
+        };
+        if version == Version::CURRENT {
+            return Ok(vec![serde_json::from_value(Value::Object(value))?]);
+        }
+
+        rewrite(&mut value);
+        if is_item(&value) && !value.contains_key("shard") {
+            let first = match value.get("inVs").and_then(|v| v.get(0)) {
+                Some(v) => serde_json::from_value(v.clone())?,
+                None => return Err(Error::MissingShard { id: id(&value)? }),
+            };
+            match self.documents.get(&first) {
+                Some(document) => {
+                    value.insert("shard".into(), document.clone());
+                }
+                None => {
+                    self.pending.entry(first).or_default().push(value);
+                    return Ok(Vec::new());
+                }
+            }
+        }
+
+        let completed = match value.get("label").and_then(Value::as_str) {
+            Some("contains") => self.contains(&value)?,
+            _ => Vec::new(),
+        };
+        let mut entries = vec![serde_json::from_value(Value::Object(value))?];
+        entries.extend(completed);
+        Ok(entries)
+    }
+
+    /// Record the document of the ranges of a `contains` edge, and
+    /// complete the `item` edges that were waiting for it.
+    fn contains(&mut self, value: &Map<String, Value>) -> Result<Vec<Entry>, Error> {
+        let mut entries = Vec::new();
+        let document = match value.get("outV") {
+            Some(d) => d,
+            None => return Ok(entries),
+        };
+        let ranges = value.get("inVs").and_then(Value::as_array);
+        for range in ranges.into_iter().flatten() {
+            let range: Id = serde_json::from_value(range.clone())?;
+            for mut item in self.pending.remove(&range).unwrap_or_default() {
+                item.insert("shard".into(), document.clone());
+                entries.push(serde_json::from_value(Value::Object(item))?);
+            }
+            self.documents.insert(range, document.clone());
+        }
+        Ok(entries)
+    }
+
+    /// End the dump, failing if some `item` edges are still without a document.
+    pub fn finish(self) -> Result<(), Error> {
+        match self.pending.into_values().flatten().next() {
+            Some(item) => Err(Error::MissingShard { id: id(&item)? }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Read a whole dump of any version into the current model.
+///
+/// The entries are in the order of the dump, except for the `item`
+/// edges of 0.4 without a document, which follow their `contains` edge.
+pub fn upgrade(dump: &str) -> Result<Vec<Entry>, Error> {
+    let mut upgrader = Upgrader::new();
+    let mut entries = Vec::new();
+    for line in dump.lines().filter(|l| !l.trim().is_empty()) {
+        entries.extend(upgrader.push(line)?);
+    }
+    upgrader.finish()?;
+    Ok(entries)
+}